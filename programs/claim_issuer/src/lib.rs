@@ -1,13 +1,16 @@
+// The `cpi` module generated by `#[program]` mirrors the arguments of the claim instructions
+#![allow(clippy::too_many_arguments)]
+
 use anchor_lang::{prelude::*, solana_program::hash::hash, Key as AnchorKey,};
-use crate::utils::verify_ed25519_ix;
-use solana_program::sysvar::instructions::{load_instruction_at_checked, ID as IX_ID};
-use solana_program::ed25519_program::ID as ED25519_ID;
-use solana_program::instruction::Instruction;
+use crate::utils::verify_ed25519_in_tx;
+use solana_program::sysvar::instructions::ID as IX_ID;
 pub mod utils;
 pub mod error;
 
 declare_id!("3BWQvZ8vUQewDzAcDmoYa2PzKFazgaCBnKQNFQx15hXM");
 
+pub const REVOKED_CLAIM_SEED: &[u8] = b"revoked";
+
 #[program]
 pub mod claim_issuer {
     pub use super::*;
@@ -43,7 +46,7 @@ pub mod claim_issuer {
         Ok(())
     }
 
-    pub fn get_key(_ctx: Context< _Key>, key: Pubkey) -> Result<NewKey> {        
+    pub fn get_key(_ctx: Context<GetKeys>, key: Pubkey) -> Result<NewKey> {        
         let key_hash = hash_key(&key);
         let keys_account = &_ctx.accounts.keys;
         keys_account.keys.iter()
@@ -52,12 +55,12 @@ pub mod claim_issuer {
             .ok_or(Error::KeyNotFound.into())
     }
 
-    pub fn get_keys(_ctx: Context<_Key>) -> Result<Vec<NewKey>> {
+    pub fn get_keys(_ctx: Context<GetKeys>) -> Result<Vec<NewKey>> {
         Ok(_ctx.accounts.keys.keys.clone())   
     }
 
-    pub fn add_key(_ctx: Context<_Key>, manager: Pubkey, key: Pubkey, purpose: u32, key_type: u32 ) -> Result<()> {
-        identity_require_auth(&_ctx.accounts.keys, &manager, KeyPurpose::Management)?;
+    pub fn add_key(_ctx: Context<_Key>, key: Pubkey, purpose: u32, key_type: u32 ) -> Result<()> {
+        identity_require_auth(_ctx.accounts, KeyPurpose::Management)?;
         
         let key_hash = hash_key( &key);
         let key_purpose = KeyPurpose::try_from(purpose).map_err(|_| anchor_lang::error::Error::from(Error::InvalidKeyPurpose))?;
@@ -80,16 +83,19 @@ pub mod claim_issuer {
         }
 
         if !key_found {
+            if keys.keys.len() >= KeysAccount::MAX_KEYS {
+                return Err(Error::TooManyKeys.into());
+            }
             let new_key = NewKey {
                 purposes: vec![key_purpose],
                 key_type: key_type_enum,
-                key: key_hash.clone(),
+                key: key_hash,
             };
             keys.keys.push(new_key);
         }
 
         emit!(KeyAdded {
-            manager,
+            manager: _ctx.accounts.signer.key(),
             key,
             purpose,
             key_type,
@@ -98,8 +104,8 @@ pub mod claim_issuer {
         Ok(())
     }
 
-    pub fn remove_key<'info>(_ctx: Context< _Key>, manager: Pubkey, key: Pubkey, purpose: u32) -> Result<()> {
-        identity_require_auth(&_ctx.accounts.keys, &manager, KeyPurpose::Management)?;
+    pub fn remove_key(_ctx: Context< _Key>, key: Pubkey, purpose: u32) -> Result<()> {
+        identity_require_auth(_ctx.accounts, KeyPurpose::Management)?;
 
         let key_hash = hash_key( &key);
         let key_purpose = KeyPurpose::try_from(purpose).map_err(|_| Error::InvalidKeyPurpose)?;
@@ -126,16 +132,16 @@ pub mod claim_issuer {
         }
 
         emit!(KeyRemoved {
-            manager,
+            manager: _ctx.accounts.signer.key(),
             key,
             purpose,
         });
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn add_claim(
         ctx: Context<ClaimContext>,
-        sender: Pubkey, 
         topic: u64,
         scheme: u64,
         issuer_wallet: Pubkey,
@@ -143,11 +149,15 @@ pub mod claim_issuer {
         signature: [u8; 64],
         data: Vec<u8>,
         uri: String,
+        valid_from: i64,
+        valid_until: i64,
+        ed25519_ix_index: Option<u8>,
     ) -> Result<[u8; 32]> {
-        identity_require_auth(&ctx.accounts.key_context.keys, &sender, KeyPurpose::Claim)?;
+        identity_require_auth(&ctx.accounts.key_context, KeyPurpose::Claim)?;
+        let sender = ctx.accounts.key_context.signer.key();
         let current_program_id = ctx.program_id;
         if *current_program_id != issuer {
-            let is_valid = is_claim_valid(
+            let is_valid = is_claim_signature_valid(
                 &ctx.accounts.instructions,
                 &ctx.accounts.key_context.keys,
                 issuer_wallet,
@@ -155,6 +165,9 @@ pub mod claim_issuer {
                 topic,
                 signature,
                 data.clone(),
+                valid_from,
+                valid_until,
+                ed25519_ix_index,
             )?;
             if !is_valid {
                 return Err(Error::InvalidClaim.into());
@@ -172,7 +185,32 @@ pub mod claim_issuer {
             signature,
             data: data.clone(),
             uri: uri.clone(),
+            valid_from,
+            valid_until,
         };
+
+        // A claim with the same issuer and topic replaces the existing one (ERC-735)
+        if let Some((_, claim)) = claims_account.claims.iter_mut().find(|(id, _)| *id == claim_id) {
+            let old_signature = claim.signature;
+            *claim = new_claim;
+
+            emit!(ClaimChanged {
+                sender,
+                claim_id,
+                topic,
+                scheme,
+                issuer,
+                issuer_wallet,
+                old_signature,
+                signature,
+                data,
+                uri,
+                valid_from,
+                valid_until,
+            });
+
+            return Ok(claim_id);
+        }
         claims_account.claims.push((claim_id, new_claim));
     
         // Emit event for added claim
@@ -186,25 +224,28 @@ pub mod claim_issuer {
             signature,
             data,
             uri,
+            valid_from,
+            valid_until,
         });
     
         Ok(claim_id)
     }
 
 
-    pub fn remove_claim(ctx: Context<ClaimContext>, sender: Pubkey, claim_id: [u8; 32]) -> Result<()> {
+    pub fn remove_claim(ctx: Context<ClaimContext>, claim_id: [u8; 32]) -> Result<()> {
         // Ensure the action is authorized
-        identity_require_auth(&ctx.accounts.key_context.keys, &sender, KeyPurpose::Claim)?;
+        identity_require_auth(&ctx.accounts.key_context, KeyPurpose::Claim)?;
+        let sender = ctx.accounts.key_context.signer.key();
 
         // Locate the claim
         let claims_account = &mut ctx.accounts.claims_account;
 
-        // Find the index of the claim
-        let pos = claims_account.claims.iter().position(|(id, _)| *id == claim_id)
-            .ok_or(Error::ClaimNotFound)?;
+        if !claims_account.claims.iter().any(|(id, _)| *id == claim_id) {
+            return Err(Error::ClaimNotFound.into());
+        }
 
-        // Remove the claim from the list
-        claims_account.claims.remove(pos);
+        // Remove every entry of the claim from the list
+        claims_account.claims.retain(|(id, _)| *id != claim_id);
 
         // Emit an event for removed claim
         emit!(ClaimRemoved {
@@ -215,7 +256,7 @@ pub mod claim_issuer {
         Ok(())
     }
 
-    pub fn get_claim_by_id(_ctx: Context<ClaimContext>, claim_id: [u8; 32]) -> Result<Option<Claim>> {
+    pub fn get_claim_by_id(_ctx: Context<GetClaim>, claim_id: [u8; 32]) -> Result<Option<Claim>> {
         let claims_account = &_ctx.accounts.claims_account;
         let claim = claims_account.claims.iter()
             .find(|(id, _)| *id == claim_id)
@@ -224,47 +265,91 @@ pub mod claim_issuer {
     }
 
 
-    pub fn revoke_claim(
-        ctx: Context<ClaimContext>,
-        sender: Pubkey,
-        claim_id: [u8; 32],
-    ) -> Result<()> {
-        // Ensure the action is authorized
-        identity_require_auth(&ctx.accounts.key_context.keys, &sender, KeyPurpose::Management)?;
-    
+    /// Revokes the claim `claim_id` of this issuer's claims account.
+    pub fn revoke_claim(ctx: Context<RevokeClaim>, claim_id: [u8; 32]) -> Result<()> {
         // Locate the claim
-        let claims_account = &mut ctx.accounts.claims_account;
-    
-        // Find the claim
-        let claim = claims_account.claims.iter()
+        let signature = ctx.accounts.claims_account.claims.iter()
             .find(|(id, _)| *id == claim_id)
+            .map(|(_, claim)| claim.signature)
             .ok_or(Error::ClaimNotFound)?;
-    
-        // Check if the claim is already revoked
-        let revoked_claims = &mut ctx.accounts.revoked_claims;
-        if revoked_claims.revoked_claims.iter().any(|&s| s == claim.1.signature) {
-            return Err(Error::ClaimAlreadyRevoked.into());
-        }
-    
-        // Add the claim's signature to the revoked claims
-        revoked_claims.revoked_claims.push(claim.1.signature.clone());
-    
+
+        revoke(
+            &ctx.accounts.key_context,
+            &mut ctx.accounts.revoked_claim,
+            ctx.bumps.revoked_claim,
+            signature,
+        )?;
+
         // Emit an event for revoked claim
         emit!(ClaimRevoked {
-            sender,
+            sender: ctx.accounts.key_context.signer.key(),
             claim_id,
         });
-    
+
+        Ok(())
+    }
+
+    /// ERC-735 `revokeClaimBySignature`: revokes any claim signed with
+    /// `signature` by one of this issuer's Claim keys, including claims this
+    /// issuer doesn't hold a copy of.
+    pub fn revoke_claim_by_signature(ctx: Context<RevokeClaimBySignature>, signature: [u8; 64]) -> Result<()> {
+        revoke(
+            &ctx.accounts.key_context,
+            &mut ctx.accounts.revoked_claim,
+            ctx.bumps.revoked_claim,
+            signature,
+        )?;
+
+        emit!(ClaimRevokedBySignature {
+            sender: ctx.accounts.key_context.signer.key(),
+            signature,
+        });
+
         Ok(())
     }
 
-    
     pub fn is_claim_revoked(
-        ctx: Context<ClaimContext>,
+        ctx: Context<IsClaimRevoked>,
+        _signature: [u8; 64],
+    ) -> Result<bool> {
+        Ok(is_revoked(&ctx.accounts.revoked_claim))
+    }
+
+    /// ERC-735 `isClaimValid`: whether `signature` is a claim about `identity`
+    /// on `topic` signed by a Claim key of this issuer, neither revoked since
+    /// nor outside its validity window.
+    /// Read-only, meant to be called by identities and verifiers through CPI.
+    #[allow(clippy::too_many_arguments)]
+    pub fn is_claim_valid(
+        ctx: Context<IsClaimValid>,
+        identity: Pubkey,
+        topic: u64,
+        issuer_wallet: Pubkey,
         signature: [u8; 64],
+        data: Vec<u8>,
+        valid_from: i64,
+        valid_until: i64,
+        ed25519_ix_index: Option<u8>,
     ) -> Result<bool> {
-        let revoked_claims = &ctx.accounts.revoked_claims;
-        Ok(revoked_claims.revoked_claims.iter().any(|&s| s == signature))
+        if !is_vouched_for(&ctx.accounts.keys, &ctx.accounts.revoked_claim, &issuer_wallet) {
+            return Ok(false);
+        }
+
+        let is_valid = is_claim_signature_valid(
+            &ctx.accounts.instructions,
+            &ctx.accounts.keys,
+            issuer_wallet,
+            identity,
+            topic,
+            signature,
+            data,
+            valid_from,
+            valid_until,
+            ed25519_ix_index,
+        )
+        .unwrap_or(false);
+
+        Ok(is_valid)
     }
 
 }
@@ -295,6 +380,24 @@ pub struct ClaimAdded {
     pub signature: [u8; 64],
     pub data: Vec<u8>,
     pub uri: String,
+    pub valid_from: i64,
+    pub valid_until: i64,
+}
+
+#[event]
+pub struct ClaimChanged {
+    pub sender: Pubkey,
+    pub claim_id: [u8; 32],
+    pub topic: u64,
+    pub scheme: u64,
+    pub issuer: Pubkey,
+    pub issuer_wallet: Pubkey,
+    pub old_signature: [u8; 64],
+    pub signature: [u8; 64],
+    pub data: Vec<u8>,
+    pub uri: String,
+    pub valid_from: i64,
+    pub valid_until: i64,
 }
 
 #[event]
@@ -309,22 +412,70 @@ pub struct ClaimRevoked {
     pub claim_id: [u8; 32],
 }
 
+#[event]
+pub struct ClaimRevokedBySignature {
+    pub sender: Pubkey,
+    pub signature: [u8; 64],
+}
+
+/// A revoked claim signature, at the `[REVOKED_CLAIM_SEED, keys, hash(signature)]`
+/// PDA. A signature is revoked once this account exists.
 #[account]
-pub struct RevokedClaimsAccount {
-    pub revoked_claims: Vec<[u8; 64]>,
+pub struct RevokedClaim {
+    pub bump: u8,
+    pub signature: [u8; 64],
 }
 
-fn identity_require_auth(keys_account: &Account<KeysAccount>, sender: &Pubkey, key_type: KeyPurpose) -> Result<()> {
-    let key_hash = hash_key(sender);
+impl RevokedClaim {
+    const LEN: usize = 8 + 1 + 64;
+}
 
-    if !key_has_purpose(keys_account, &key_hash, key_type) {
+/// Requires the signer of `key_context` to hold a key with `key_type` in its keys account.
+fn identity_require_auth(key_context: &_Key, key_type: KeyPurpose) -> Result<()> {
+    let key_hash = hash_key(&key_context.signer.key());
+
+    if !key_has_purpose(&key_context.keys, &key_hash, key_type) {
         return Err(Error::InsufficientPermissions.into());
     }
 
     Ok(())
 }
 
-fn key_has_purpose(keys_account: &Account<KeysAccount>, key_hash: &[u8; 32], purpose: KeyPurpose) -> bool {
+/// Records `signature` as revoked by a Management key of `key_context`.
+fn revoke(
+    key_context: &_Key,
+    revoked_claim: &mut Account<RevokedClaim>,
+    bump: u8,
+    signature: [u8; 64],
+) -> Result<()> {
+    identity_require_auth(key_context, KeyPurpose::Management)?;
+
+    // Check if the claim is already revoked
+    if revoked_claim.signature == signature {
+        return Err(Error::ClaimAlreadyRevoked.into());
+    }
+
+    revoked_claim.bump = bump;
+    revoked_claim.signature = signature;
+
+    Ok(())
+}
+
+/// Whether `revoked_claim`, a `[REVOKED_CLAIM_SEED, keys, hash(signature)]`
+/// PDA, records a revocation.
+fn is_revoked(revoked_claim: &AccountInfo) -> bool {
+    revoked_claim.owner == &crate::ID && !revoked_claim.data_is_empty()
+}
+
+/// Whether the issuer of `keys` still vouches for claims signed by
+/// `issuer_wallet` with the signature of `revoked_claim`: the key holds the
+/// Claim purpose and the signature isn't revoked. The claim's signature itself
+/// is left to `is_claim_valid`.
+pub fn is_vouched_for(keys: &KeysAccount, revoked_claim: &AccountInfo, issuer_wallet: &Pubkey) -> bool {
+    key_has_purpose(keys, &hash_key(issuer_wallet), KeyPurpose::Claim) && !is_revoked(revoked_claim)
+}
+
+fn key_has_purpose(keys_account: &KeysAccount, key_hash: &[u8; 32], purpose: KeyPurpose) -> bool {
     keys_account.keys.iter().any(|k| k.key == *key_hash && k.purposes.contains(&purpose))
 }
 fn hash_key(key: &Pubkey) -> [u8; 32] {
@@ -334,6 +485,11 @@ fn hash_key(key: &Pubkey) -> [u8; 32] {
     hashed.to_bytes()
 }
 
+/// Seed of the `RevokedClaim` PDA of `signature`.
+pub fn hash_signature(signature: &[u8]) -> [u8; 32] {
+    hash(signature).to_bytes()
+}
+
 pub fn hash_claim(issuer: &Pubkey, topic: u64) -> [u8; 32] {
     let issuer_bytes = issuer.to_bytes();
 
@@ -349,7 +505,8 @@ pub fn hash_claim(issuer: &Pubkey, topic: u64) -> [u8; 32] {
 }
 
 
-pub fn is_claim_valid(
+#[allow(clippy::too_many_arguments)]
+pub fn is_claim_signature_valid(
     instructions: &AccountInfo,
     keys: &Account<KeysAccount>,
     issuer_wallet: Pubkey,
@@ -357,24 +514,23 @@ pub fn is_claim_valid(
     topic: u64,
     signature: [u8; 64],
     data: Vec<u8>,
+    valid_from: i64,
+    valid_until: i64,
+    ed25519_ix_index: Option<u8>,
 ) -> Result<bool> {
-    // Concatenate data
-    let mut concatenated_bytes = Vec::new();
-    concatenated_bytes.extend_from_slice(&identity.to_bytes());
-    concatenated_bytes.extend_from_slice(&topic.to_le_bytes());
-    concatenated_bytes.extend_from_slice(&data);
-
-    // Verify the Ed25519 signature
-    let instruction_sysvar_info = instructions.to_account_info();
-    let ix: Instruction = load_instruction_at_checked(0, &instruction_sysvar_info)?;
-
-    // Check that the instruction is an Ed25519Program instruction
-    if ix.program_id != ED25519_ID {
-        return Err(Error::InvalidSignature.into());
+    // Expired claims are invalid without being revoked
+    if !is_within_validity(valid_from, valid_until)? {
+        return Err(Error::ClaimExpired.into());
     }
 
-    // Verify the Ed25519Program instruction fields
-    verify_ed25519_ix(&ix, &issuer_wallet.to_bytes(), &concatenated_bytes, &signature)?;
+    // Verify the Ed25519 signature, found at `ed25519_ix_index` or anywhere in the transaction
+    verify_ed25519_in_tx(
+        instructions,
+        ed25519_ix_index,
+        &issuer_wallet.to_bytes(),
+        &claim_message(&identity, topic, &data, valid_from, valid_until),
+        &signature,
+    )?;
 
     // Check if the issuer's key has the Claim purpose
     let issuer_wallet_hash = hash_key(&issuer_wallet);
@@ -385,16 +541,36 @@ pub fn is_claim_valid(
     }
 }
 
+/// Message signed by Ed25519 claim keys: `identity || topic || data length (u32)
+/// || data`, followed by the validity window when one is set. The length
+/// prefix keeps the window from being read as part of the data.
+pub fn claim_message(identity: &Pubkey, topic: u64, data: &[u8], valid_from: i64, valid_until: i64) -> Vec<u8> {
+    let mut message = Vec::new();
+    message.extend_from_slice(&identity.to_bytes());
+    message.extend_from_slice(&topic.to_le_bytes());
+    message.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    message.extend_from_slice(data);
+    if valid_from != 0 || valid_until != 0 {
+        message.extend_from_slice(&valid_from.to_le_bytes());
+        message.extend_from_slice(&valid_until.to_le_bytes());
+    }
+
+    message
+}
+
+/// Whether the clock is within a claim's validity window, where `0` leaves
+/// either end open.
+pub fn is_within_validity(valid_from: i64, valid_until: i64) -> Result<bool> {
+    let now = Clock::get()?.unix_timestamp;
+    Ok((valid_from == 0 || now >= valid_from) && (valid_until == 0 || now < valid_until))
+}
 
 /// External instruction that only gets executed if
 /// an `Ed25519Program.createInstructionWithPublicKey`
 /// instruction was sent in the same transaction.
 pub fn verify_ed25519(ctx: Context<Verify>, pubkey: [u8; 32], msg: Vec<u8>, sig: [u8; 64]) -> Result<()> {
-    // Get what should be the Ed25519Program instruction
-    let ix: Instruction = load_instruction_at_checked(0, &ctx.accounts.ix_sysvar).map_err(|e| anchor_lang::prelude::ProgramError::from(e))?;
-
-    // Check that ix is what we expect to have been sent
-    utils::verify_ed25519_ix(&ix, &pubkey, &msg, &sig)?;
+    // Check that an Ed25519Program instruction of the transaction verified the signature
+    utils::verify_ed25519_in_tx(&ctx.accounts.ix_sysvar, None, &pubkey, &msg, &sig)?;
 
     // Do other stuff
     
@@ -405,8 +581,9 @@ pub fn verify_ed25519(ctx: Context<Verify>, pubkey: [u8; 32], msg: Vec<u8>, sig:
 pub struct Initialize<'info> {
     #[account(init, payer = user, space = 8 + 1)] // 8 bytes for discriminator, 1 for bool
     pub identity_account: Account<'info, IdentityAccount>,
-    #[account(init, payer = user, space = NewKey::LEN + 1)] // 8 bytes for discriminator, 1 for bool
+    #[account(init, payer = user, space = 8 + NewKey::LEN)]
     pub key_account: Account<'info, NewKey>,
+    #[account(init, payer = user, space = KeysAccount::LEN)]
     pub keys: Account<'info, KeysAccount>,
     #[account(init, payer = user, space = Claim::LEN + 8)]
     pub claims_account: Account<'info, ClaimsAccount>,
@@ -419,6 +596,13 @@ pub struct Initialize<'info> {
 pub struct _Key<'info> {
     #[account(mut)]
     pub keys: Account<'info, KeysAccount>,
+    /// A key of `keys` holding the purpose the instruction requires.
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetKeys<'info> {
+    pub keys: Account<'info, KeysAccount>,
 }
 
 #[derive(Accounts)]
@@ -429,8 +613,69 @@ pub struct ClaimContext<'info> {
     /// CHECK: This is safe because we are only reading the instructions sysvar.
     #[account(address = solana_program::sysvar::instructions::id())]
     pub instructions: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetClaim<'info> {
+    pub claims_account: Account<'info, ClaimsAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(claim_id: [u8; 32])]
+pub struct RevokeClaim<'info> {
+    pub claims_account: Account<'info, ClaimsAccount>,
+    pub key_context: _Key<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = RevokedClaim::LEN,
+        seeds = [REVOKED_CLAIM_SEED, key_context.keys.key().as_ref(), &claims_account.signature_hash(&claim_id)],
+        bump
+    )]
+    pub revoked_claim: Account<'info, RevokedClaim>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(signature: [u8; 64])]
+pub struct RevokeClaimBySignature<'info> {
+    pub key_context: _Key<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = RevokedClaim::LEN,
+        seeds = [REVOKED_CLAIM_SEED, key_context.keys.key().as_ref(), &hash_signature(&signature)],
+        bump
+    )]
+    pub revoked_claim: Account<'info, RevokedClaim>,
     #[account(mut)]
-    pub revoked_claims: Account<'info, RevokedClaimsAccount>,
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(signature: [u8; 64])]
+pub struct IsClaimRevoked<'info> {
+    pub keys: Account<'info, KeysAccount>,
+    /// CHECK: The `[REVOKED_CLAIM_SEED, keys, hash(signature)]` PDA, which only
+    /// exists once the signature is revoked.
+    #[account(seeds = [REVOKED_CLAIM_SEED, keys.key().as_ref(), &hash_signature(&signature)], bump)]
+    pub revoked_claim: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(identity: Pubkey, topic: u64, issuer_wallet: Pubkey, signature: [u8; 64])]
+pub struct IsClaimValid<'info> {
+    pub keys: Account<'info, KeysAccount>,
+    /// CHECK: The `[REVOKED_CLAIM_SEED, keys, hash(signature)]` PDA, which only
+    /// exists once the signature is revoked.
+    #[account(seeds = [REVOKED_CLAIM_SEED, keys.key().as_ref(), &hash_signature(&signature)], bump)]
+    pub revoked_claim: UncheckedAccount<'info>,
+    /// CHECK: This is safe because we are only reading the instructions sysvar.
+    #[account(address = solana_program::sysvar::instructions::id())]
+    pub instructions: AccountInfo<'info>,
 }
 /// Context accounts
 #[derive(Accounts)]
@@ -486,7 +731,7 @@ pub struct NewKey {
 }
 
 impl NewKey {
-    const LEN: usize = 4 + (1 * 4) + 4 + 32;
+    const LEN: usize = 4 + 4 + 4 + 32;
 }
 
 impl TryFrom<u32> for KeyType {
@@ -505,11 +750,28 @@ pub struct KeysAccount {
     pub keys: Vec<NewKey>,
 }
 
+impl KeysAccount {
+    pub const MAX_KEYS: usize = 16;
+    const LEN: usize = 8 + 4 + Self::MAX_KEYS * NewKey::LEN;
+}
+
 #[account]
 pub struct ClaimsAccount {
     pub claims: Vec<([u8; 32], Claim)>, 
 }
 
+impl ClaimsAccount {
+    /// Seed of the `RevokedClaim` PDA of the claim `claim_id`'s signature, or
+    /// of an empty signature when there is no such claim.
+    pub fn signature_hash(&self, claim_id: &[u8; 32]) -> [u8; 32] {
+        let signature = self.claims.iter()
+            .find(|(id, _)| id == claim_id)
+            .map(|(_, claim)| &claim.signature[..])
+            .unwrap_or_default();
+        hash_signature(signature)
+    }
+}
+
 #[account]
 pub struct Claim {
     topic: u64,          
@@ -519,10 +781,12 @@ pub struct Claim {
     signature: [u8; 64], 
     data: Vec<u8>,      
     uri: String,
+    valid_from: i64,
+    valid_until: i64,
 }
 
 impl Claim {
-    const LEN: usize = 8 + 8 + 32 + 32 + 64 + 2048 + 512;
+    const LEN: usize = 8 + 8 + 32 + 32 + 64 + 2048 + 512 + 8 + 8;
 }
 
 #[error_code]
@@ -555,4 +819,8 @@ pub enum Error {
     InvalidIssuer,
     #[msg("The address bytes are invalid.")]
     InvalidAddressBytes,
+    #[msg("The claim is expired or not yet valid.")]
+    ClaimExpired,
+    #[msg("The issuer has reached its maximum number of keys.")]
+    TooManyKeys,
 }
\ No newline at end of file
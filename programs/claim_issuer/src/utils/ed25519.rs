@@ -1,76 +1,144 @@
 use anchor_lang::prelude::*;
 use solana_program::instruction::Instruction;
 use solana_program::ed25519_program::ID as ED25519_ID;
+use solana_program::sysvar::instructions::{load_instruction_at_checked, ID as IX_ID};
 use crate::error::ErrorCode;
 
-use std::convert::TryInto;
+/// Size of one signature entry in the Ed25519Program header: 7*u16
+const SIGNATURE_OFFSETS_SIZE: usize = 14;
+
+/// Verify that `sig` by `pubkey` over `msg` was checked by an Ed25519Program
+/// instruction of the current transaction.
+///
+/// With an `ix_index` hint only that instruction is looked at, otherwise every
+/// instruction of the transaction is scanned.
+pub fn verify_ed25519_in_tx(
+    instructions: &AccountInfo,
+    ix_index: Option<u8>,
+    pubkey: &[u8],
+    msg: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    if let Some(index) = ix_index {
+        let ix: Instruction = load_instruction_at_checked(index as usize, instructions)?;
+        return verify_ed25519_ix(instructions, &ix, pubkey, msg, sig);
+    }
+
+    for index in 0..num_instructions(instructions)? {
+        let ix: Instruction = load_instruction_at_checked(index, instructions)?;
+        if ix.program_id == ED25519_ID && verify_ed25519_ix(instructions, &ix, pubkey, msg, sig).is_ok() {
+            return Ok(());
+        }
+    }
+
+    Err(ErrorCode::SigVerificationFailed.into())
+}
 
 /// Verify Ed25519Program instruction fields
-pub fn verify_ed25519_ix(ix: &Instruction, pubkey: &[u8], msg: &[u8], sig: &[u8]) -> Result<()> {
-    if  ix.program_id       != ED25519_ID                   ||  // The program id we expect
-        ix.accounts.len()   != 0                            ||  // With no context accounts
-        ix.data.len()       != (16 + 64 + 32 + msg.len())       // And data of this size
+pub fn verify_ed25519_ix(
+    instructions: &AccountInfo,
+    ix: &Instruction,
+    pubkey: &[u8],
+    msg: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    if  ix.program_id       != ED25519_ID   ||  // The program id we expect
+        !ix.accounts.is_empty()             // With no context accounts
     {
         return Err(ErrorCode::SigVerificationFailed.into());    // Otherwise, we can already throw err
     }
 
-    check_ed25519_data(&ix.data, pubkey, msg, sig)?;            // If that's not the case, check data
-
-    Ok(())
+    check_ed25519_data(instructions, &ix.data, pubkey, msg, sig)  // If that's not the case, check data
 }
 
-/// Verify serialized Ed25519Program instruction data
-pub fn check_ed25519_data(data: &[u8], pubkey: &[u8], msg: &[u8], sig: &[u8]) -> Result<()> {
+/// Verify serialized Ed25519Program instruction data, succeeding if any of its
+/// signature entries is `(pubkey, msg, sig)`
+pub fn check_ed25519_data(
+    instructions: &AccountInfo,
+    data: &[u8],
+    pubkey: &[u8],
+    msg: &[u8],
+    sig: &[u8],
+) -> Result<()> {
     // According to this layout used by the Ed25519Program
     // https://github.com/solana-labs/solana-web3.js/blob/master/src/ed25519-program.ts#L33
+    // Byte 0 holds the number of signatures and byte 1 is padding, followed by
+    // one entry of offsets per signature
 
-    // "Deserializing" byte slices
-
-    let num_signatures                  = &[data[0]];        // Byte  0
-    let padding                         = &[data[1]];        // Byte  1
-    let signature_offset                = &data[2..=3];      // Bytes 2,3
-    let signature_instruction_index     = &data[4..=5];      // Bytes 4,5
-    let public_key_offset               = &data[6..=7];      // Bytes 6,7
-    let public_key_instruction_index    = &data[8..=9];      // Bytes 8,9
-    let message_data_offset             = &data[10..=11];    // Bytes 10,11
-    let message_data_size               = &data[12..=13];    // Bytes 12,13
-    let message_instruction_index       = &data[14..=15];    // Bytes 14,15
-
-    let data_pubkey                     = &data[16..16+32];  // Bytes 16..16+32
-    let data_sig                        = &data[48..48+64];  // Bytes 48..48+64
-    let data_msg                        = &data[112..];      // Bytes 112..end
-
-    // Expected values
-
-    let exp_public_key_offset:      u16 = 16; // 2*u8 + 7*u16
-    let exp_signature_offset:       u16 = exp_public_key_offset + pubkey.len() as u16;
-    let exp_message_data_offset:    u16 = exp_signature_offset + sig.len() as u16;
-    let exp_num_signatures:          u8 = 1;
-    let exp_message_data_size:      u16 = msg.len().try_into().unwrap();
-
-    // Header and Arg Checks
-
-    // Header
-    if  num_signatures                  != &exp_num_signatures.to_le_bytes()        ||
-        padding                         != &[0]                                     ||
-        signature_offset                != &exp_signature_offset.to_le_bytes()      ||
-        signature_instruction_index     != &u16::MAX.to_le_bytes()                  ||
-        public_key_offset               != &exp_public_key_offset.to_le_bytes()     ||
-        public_key_instruction_index    != &u16::MAX.to_le_bytes()                  ||
-        message_data_offset             != &exp_message_data_offset.to_le_bytes()   ||
-        message_data_size               != &exp_message_data_size.to_le_bytes()     ||
-        message_instruction_index       != &u16::MAX.to_le_bytes()  
-    {
+    if data.len() < 2 {
         return Err(ErrorCode::SigVerificationFailed.into());
     }
+    let num_signatures = data[0] as usize;
 
-    // Arguments
-    if  data_pubkey != pubkey   ||
-        data_msg    != msg      ||
-        data_sig    != sig
-    {
+    for i in 0..num_signatures {
+        let start = 2 + i * SIGNATURE_OFFSETS_SIZE;
+        let offsets = data
+            .get(start..start + SIGNATURE_OFFSETS_SIZE)
+            .ok_or(ErrorCode::SigVerificationFailed)?;
+        let read_u16 = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+
+        let signature_offset                = read_u16(0);   // Bytes 0,1
+        let signature_instruction_index     = read_u16(2);   // Bytes 2,3
+        let public_key_offset               = read_u16(4);   // Bytes 4,5
+        let public_key_instruction_index    = read_u16(6);   // Bytes 6,7
+        let message_data_offset             = read_u16(8);   // Bytes 8,9
+        let message_data_size               = read_u16(10);  // Bytes 10,11
+        let message_instruction_index       = read_u16(12);  // Bytes 12,13
+
+        // Arguments
+        let data_pubkey = entry_bytes(instructions, data, public_key_instruction_index, public_key_offset, 32)?;
+        let data_sig    = entry_bytes(instructions, data, signature_instruction_index, signature_offset, 64)?;
+        let data_msg    = entry_bytes(
+            instructions,
+            data,
+            message_instruction_index,
+            message_data_offset,
+            message_data_size as usize,
+        )?;
+
+        if  data_pubkey == pubkey   &&
+            data_msg    == msg      &&
+            data_sig    == sig
+        {
+            return Ok(());
+        }
+    }
+
+    Err(ErrorCode::SigVerificationFailed.into())
+}
+
+/// Bytes `offset..offset + len` of the instruction at `ix_index`, where
+/// `u16::MAX` designates the Ed25519Program instruction itself
+fn entry_bytes(
+    instructions: &AccountInfo,
+    data: &[u8],
+    ix_index: u16,
+    offset: u16,
+    len: usize,
+) -> Result<Vec<u8>> {
+    let range = offset as usize..offset as usize + len;
+
+    let bytes = if ix_index == u16::MAX {
+        data.get(range).map(<[u8]>::to_vec)
+    } else {
+        let ix: Instruction = load_instruction_at_checked(ix_index as usize, instructions)?;
+        ix.data.get(range).map(<[u8]>::to_vec)
+    };
+
+    bytes.ok_or_else(|| ErrorCode::SigVerificationFailed.into())
+}
+
+/// Number of instructions in the current transaction, read from the
+/// instructions sysvar header
+fn num_instructions(instructions: &AccountInfo) -> Result<usize> {
+    if *instructions.key != IX_ID {
+        return Err(ErrorCode::SigVerificationFailed.into());
+    }
+
+    let data = instructions.try_borrow_data()?;
+    if data.len() < 2 {
         return Err(ErrorCode::SigVerificationFailed.into());
     }
 
-    Ok(())
+    Ok(u16::from_le_bytes([data[0], data[1]]) as usize)
 }
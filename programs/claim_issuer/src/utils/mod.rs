@@ -1,6 +1,6 @@
-/// This mod contains functions that validate that an instruction
-/// is constructed the way we expect. In this case, this is for
-/// `Ed25519Program.createInstructionWithPublicKey()` instructions.
+//! This mod contains functions that validate that an instruction
+//! is constructed the way we expect. In this case, this is for
+//! `Ed25519Program.createInstructionWithPublicKey()` instructions.
 
 pub mod ed25519;
 
@@ -1,6 +1,5 @@
 use crate::*;
-use oapp::endpoint::{instructions::RegisterOAppParams, state::endpoint, ID as ENDPOINT_ID};
-const COUNT_SEED: &[u8] = b"Count";
+use oapp::endpoint::{instructions::RegisterOAppParams, ID as ENDPOINT_ID};
 const LZ_COMPOSE_TYPES_SEED: &[u8] = b"LzComposeTypes";
 
 #[derive(Accounts)]
@@ -11,7 +10,7 @@ pub struct InitCount<'info> {
     #[account(
         init,
         payer = payer,
-        space = Factory::calc_size(100, 100),
+        space = Factory::LEN,
         seeds = [COUNT_SEED, &params.id.to_be_bytes()],
         bump
     )]
@@ -7,25 +7,68 @@ use oapp::{
     },
     LzReceiveParams,
 };
-const COUNT_SEED: &[u8] = b"Count";
 
 #[derive(Accounts)]
 #[instruction(params: LzReceiveParams)]
 pub struct LzReceive<'info> {
     #[account(mut)]
     pub factory: Account<'info, Factory>,
+    /// CHECK: The peer configured for the message's source endpoint, which
+    /// doesn't exist for endpoints without one; see `require_peer`.
+    #[account(seeds = [REMOTE_SEED, factory.key().as_ref(), &params.src_eid.to_be_bytes()], bump)]
+    pub remote: UncheckedAccount<'info>,
     #[account(mut)]
     pub payer: Signer<'info>,
     pub system_program: Program<'info, System>,
     #[account(address = identity_lib::ID)]
     pub identity_program: Program<'info, Identity>,
-    pub keys_account: Account<'info, KeysAccount>,
-    pub claims_account: Account<'info, ClaimsAccount>,
+    /// CHECK: Checked against the factory's wallet mapping and by the identity program.
+    #[account(mut)]
+    pub identity_account: AccountInfo<'info>,
+    /// CHECK: The identity's `[KEY_SEED, identity, key_hash]` PDA of the key being
+    /// changed, or of the management key of a new identity; checked by the identity program.
+    #[account(mut)]
+    pub key_account: AccountInfo<'info>,
+    /// CHECK: The identity's `[KEYS_SEED, identity, purpose]` PDA, checked by the identity program.
+    #[account(mut)]
+    pub purpose_keys_account: AccountInfo<'info>,
+    /// CHECK: The identity's `[CLAIM_SEED, identity, claim_id]` PDA, checked by the identity program.
+    #[account(mut)]
+    pub claim_account: AccountInfo<'info>,
+    /// CHECK: The identity's `[CLAIMS_SEED, identity, topic]` PDA, checked by the identity program.
+    #[account(mut)]
+    pub claim_index_account: AccountInfo<'info>,
+    /// CHECK: The payer of the claim a RemoveClaim message removes, which is
+    /// refunded its rent; checked by the identity program.
+    #[account(mut)]
+    pub claim_payer_account: AccountInfo<'info>,
+    /// CHECK: The payer of the topic index of the claim a RemoveClaim message
+    /// removes, which is refunded its rent once the index is empty; checked by
+    /// the identity program.
+    #[account(mut)]
+    pub index_payer_account: AccountInfo<'info>,
+    /// CHECK: The `[EVM_ISSUER_SEED, factory, issuer]` PDA of the issuer of a
+    /// bridged claim, checked by the identity program.
+    pub evm_issuer_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: The `[SALT_SEED, factory, salt]` PDA of a new identity, see `take_salt`.
+    #[account(mut)]
+    pub salt_account: AccountInfo<'info>,
+    /// CHECK: The `[WALLET_SEED, factory, wallet]` PDA of the wallet the message is about, see `linked_identity`.
+    #[account(mut)]
+    pub wallet_account: AccountInfo<'info>,
+    /// CHECK: The `[IDENTITY_WALLETS_SEED, factory, identity]` PDA of a new identity, see `add_wallet`.
+    #[account(mut)]
+    pub identity_wallets_account: AccountInfo<'info>,
+    /// CHECK: The instructions sysvar, required by `identity::add_claim`.
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions: AccountInfo<'info>,
 }
 
-impl LzReceive<'_> {
-    pub fn apply(ctx: Context<LzReceive>, params: &LzReceiveParams) -> Result<()> {
-        let seeds: &[&[u8]] = &[COUNT_SEED, &ctx.accounts.factory.key().to_bytes()];
+impl<'info> LzReceive<'info> {
+    pub fn apply(ctx: &mut Context<'_, '_, 'info, 'info, LzReceive<'info>>, params: &LzReceiveParams) -> Result<()> {
+        require_peer(&ctx.accounts.remote, &params.sender)?;
+
+        let seeds: &[&[u8]] = &[COUNT_SEED, &[ctx.accounts.factory.id], &[ctx.accounts.factory.bump]];
 
         // the first 9 accounts are for clear()
         let accounts_for_clear = &ctx.remaining_accounts[0..Clear::MIN_ACCOUNTS_LEN];
@@ -50,28 +93,47 @@ impl LzReceive<'_> {
             "CreateIdentity" => {
                 let (identity_owner, salt) = decode_create_identity_payload(&payload)?;
                 let initial_management_key = ctx.accounts.factory.owner; 
-                create_identity(ctx, identity_owner, salt, initial_management_key)?;
+                create_identity(ctx.accounts, identity_owner, salt, initial_management_key)?;
+            }
+            "CreateIdentityWithManagementKeys" => {
+                let (identity_owner, salt, management_keys) =
+                    decode_create_identity_with_management_keys_payload(&payload)?;
+                // The management keys' PDAs follow the accounts for clear()
+                let key_accounts = &ctx.remaining_accounts[Clear::MIN_ACCOUNTS_LEN..];
+                create_identity_with_management_keys(ctx.accounts, key_accounts, identity_owner, salt, management_keys)?;
             }
             "AddKey" => {
                 let (wallet, key, purpose, key_type) = decode_add_key_payload(&payload)?;
-                add_key(ctx, wallet, key, purpose, key_type)?;
+                add_key(ctx.accounts, wallet, key, purpose, key_type)?;
             }
             "AddClaim" => {
-                let (wallet, topic, scheme, issuer_wallet, signature, data, uri) =
+                let (wallet, topic, scheme, issuer_wallet, issuer, signature, data, uri) =
                     decode_add_claim_payload(&payload)?;
-                add_claim(ctx, wallet, topic, scheme, issuer_wallet, signature, data, uri)?;
+                add_claim(ctx.accounts, wallet, topic, scheme, issuer_wallet, issuer, signature, data, uri)?;
             }
             "RemoveKey" => {
                 let (wallet, key, purpose) = decode_remove_key_payload(&payload)?;
-                remove_key(ctx, wallet, key, purpose)?;
+                remove_key(ctx.accounts, wallet, key, purpose)?;
             }
             "RemoveClaim" => {
-                let (wallet, topic) = decode_remove_claim_payload(&payload)?;
-                remove_claim(ctx, wallet, topic)?;
+                let (wallet, topic, issuer) = decode_remove_claim_payload(&payload)?;
+                remove_claim(ctx.accounts, wallet, topic, issuer)?;
             }
             _ => return Err(ProgramError::InvalidInstructionData.into()),
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Requires `sender` to be the peer stored in `remote`, the source endpoint's `Remote`.
+fn require_peer(remote: &AccountInfo, sender: &[u8; 32]) -> Result<()> {
+    if remote.owner != &crate::ID {
+        return Err(ErrorCode::UnknownPeer.into());
+    }
+
+    let remote = Remote::try_deserialize(&mut &remote.try_borrow_data()?[..])?;
+    require!(remote.address == *sender, ErrorCode::UnknownPeer);
+
+    Ok(())
+}
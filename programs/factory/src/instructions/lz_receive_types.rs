@@ -1,16 +1,24 @@
 use crate::*;
-use oapp::endpoint_cpi::{get_accounts_for_clear, get_accounts_for_send_compose, LzAccount};
+use identity_lib::{hash_claim, Claim, ClaimTopicIndex, CLAIMS_SEED, CLAIM_SEED, EVM_ISSUER_SEED, IDENTITY_SEED, KEYS_SEED};
+use oapp::endpoint_cpi::{get_accounts_for_clear, LzAccount};
 use oapp::{endpoint::ID as ENDPOINT_ID, LzReceiveParams};
-const REMOTE_SEED: &[u8] = b"Remote";
-const COUNT_SEED: &[u8] = b"Count";
 
 #[derive(Accounts)]
 pub struct LzReceiveTypes<'info> {
-    #[account(seeds = [COUNT_SEED, &factory.key().to_bytes()], bump)]
+    #[account(seeds = [COUNT_SEED, &[factory.id]], bump = factory.bump)]
     pub factory: Account<'info, Factory>,
 }
 
 impl LzReceiveTypes<'_> {
+    /// The accounts of `LzReceive` for `params`, in order: its named accounts,
+    /// the accounts for clear(), then the management keys' PDAs of a
+    /// CreateIdentityWithManagementKeys message.
+    ///
+    /// The identity of a message about an existing identity is read from the
+    /// `[WALLET_SEED, factory, wallet]` PDA of its wallet, which must be the first
+    /// remaining account. A RemoveClaim message also needs the claim it
+    /// removes as the second and the claim's topic index as the third, for
+    /// the payers their rent is refunded to.
     pub fn apply(
         ctx: &Context<LzReceiveTypes>,
         params: &LzReceiveParams,
@@ -20,9 +28,29 @@ impl LzReceiveTypes<'_> {
         let seeds = [REMOTE_SEED, &factory.to_bytes(), &params.src_eid.to_be_bytes()];
         let (remote, _) = Pubkey::find_program_address(&seeds, ctx.program_id);
 
+        let message_accounts = MessageAccounts::for_message(ctx, &params.message)?;
+        // The executor's payer
+        let payer = LzAccount { pubkey: Pubkey::default(), is_signer: true, is_writable: true };
+
         let mut accounts = vec![
-            LzAccount { pubkey: factory, is_signer: false, is_writable: true },
-            LzAccount { pubkey: remote, is_signer: false, is_writable: false },
+            writable(factory),
+            readonly(remote),
+            payer.clone(),
+            readonly(system_program::ID),
+            readonly(identity_lib::ID),
+            writable(message_accounts.identity),
+            writable(message_accounts.key),
+            writable(message_accounts.purpose_keys),
+            writable(message_accounts.claim),
+            writable(message_accounts.claim_index),
+            message_accounts.claim_payer.map(writable).unwrap_or(payer.clone()),
+            message_accounts.index_payer.map(writable).unwrap_or(payer),
+            // An absent optional account is passed as the program's ID
+            readonly(message_accounts.evm_issuer.unwrap_or(crate::ID)),
+            writable(message_accounts.salt),
+            writable(message_accounts.wallet),
+            writable(message_accounts.identity_wallets),
+            readonly(solana_program::sysvar::instructions::ID),
         ];
 
         let accounts_for_clear = get_accounts_for_clear(
@@ -33,20 +61,177 @@ impl LzReceiveTypes<'_> {
             params.nonce,
         );
         accounts.extend(accounts_for_clear);
+        accounts.extend(message_accounts.management_keys.into_iter().map(writable));
 
-        let is_composed = msg_codec::msg_type(&params.message) == msg_codec::COMPOSED_TYPE;
-        if is_composed {
-            let accounts_for_composing = get_accounts_for_send_compose(
-                ENDPOINT_ID,
-                &factory,
-                &factory,
-                &params.guid,
-                0,
-                &params.message,
-            );
-            accounts.extend(accounts_for_composing);
-        }
+        Ok(accounts)
+    }
+}
+
+/// The accounts of `LzReceive` that depend on the message. Those a message
+/// doesn't use are the identity's PDAs of default values, which `LzReceive`
+/// leaves untouched.
+struct MessageAccounts {
+    identity: Pubkey,
+    key: Pubkey,
+    purpose_keys: Pubkey,
+    claim: Pubkey,
+    claim_index: Pubkey,
+    /// The payer of a removed claim; the executor's payer otherwise.
+    claim_payer: Option<Pubkey>,
+    /// The payer of a removed claim's topic index; the executor's payer otherwise.
+    index_payer: Option<Pubkey>,
+    /// The issuer of a bridged claim's `EvmIssuer`.
+    evm_issuer: Option<Pubkey>,
+    salt: Pubkey,
+    wallet: Pubkey,
+    identity_wallets: Pubkey,
+    management_keys: Vec<Pubkey>,
+}
+
+impl MessageAccounts {
+    fn for_message(ctx: &Context<LzReceiveTypes>, message: &[u8]) -> Result<Self> {
+        let factory = &ctx.accounts.factory;
+        let (method_name, payload) = decode_message(message)?;
+
+        let accounts = match method_name.as_str() {
+            "CreateIdentity" => {
+                let (wallet, salt) = decode_create_identity_payload(&payload)?;
+                let mut accounts = Self::new(&factory.key(), new_identity(factory, &wallet, &salt), &wallet, &salt);
+                accounts.key = key_address(&accounts.identity, &hash_key(&factory.owner));
+                accounts
+            }
+            "CreateIdentityWithManagementKeys" => {
+                let (wallet, salt, management_keys) =
+                    decode_create_identity_with_management_keys_payload(&payload)?;
+                let mut accounts = Self::new(&factory.key(), new_identity(factory, &wallet, &salt), &wallet, &salt);
+                accounts.management_keys = management_keys
+                    .iter()
+                    .map(|key| key_address(&accounts.identity, &key.hash()))
+                    .collect();
+                accounts
+            }
+            "AddKey" => {
+                let (wallet, key, purpose, _) = decode_add_key_payload(&payload)?;
+                let mut accounts = Self::existing(ctx, &wallet)?;
+                accounts.key = key_address(&accounts.identity, &hash_key(&key));
+                accounts.purpose_keys = purpose_keys_address(&accounts.identity, purpose);
+                accounts
+            }
+            "RemoveKey" => {
+                let (wallet, key, purpose) = decode_remove_key_payload(&payload)?;
+                let mut accounts = Self::existing(ctx, &wallet)?;
+                accounts.key = key_address(&accounts.identity, &hash_key(&key));
+                accounts.purpose_keys = purpose_keys_address(&accounts.identity, purpose);
+                accounts
+            }
+            "AddClaim" => {
+                let (wallet, topic, _, _, issuer, _, _, _) = decode_add_claim_payload(&payload)?;
+                let mut accounts = Self::existing(ctx, &wallet)?;
+                accounts.claim = claim_address(&accounts.identity, &issuer, topic);
+                accounts.claim_index = claim_index_address(&accounts.identity, topic);
+                accounts.evm_issuer = Some(identity_address(&[EVM_ISSUER_SEED, factory.key().as_ref(), issuer.as_ref()]));
+                accounts
+            }
+            "RemoveClaim" => {
+                let (wallet, topic, issuer) = decode_remove_claim_payload(&payload)?;
+                let mut accounts = Self::existing(ctx, &wallet)?;
+                accounts.claim = claim_address(&accounts.identity, &issuer, topic);
+                accounts.claim_index = claim_index_address(&accounts.identity, topic);
+                accounts.claim_payer = Some(claim_payer(ctx, &accounts.claim)?);
+                accounts.index_payer = Some(index_payer(ctx, &accounts.claim_index)?);
+                accounts
+            }
+            _ => return Err(ProgramError::InvalidInstructionData.into()),
+        };
 
         Ok(accounts)
     }
-}
\ No newline at end of file
+
+    /// The accounts of a message about the identity linked to `wallet`.
+    fn existing(ctx: &Context<LzReceiveTypes>, wallet: &Pubkey) -> Result<Self> {
+        let wallet_account = ctx.remaining_accounts.first().ok_or(ErrorCode::WalletNotLinked)?;
+        let identity = linked_identity(wallet_account, &ctx.accounts.factory.key(), wallet)?;
+
+        Ok(Self::new(&ctx.accounts.factory.key(), identity, wallet, &[0u8; 32]))
+    }
+
+    fn new(factory: &Pubkey, identity: Pubkey, wallet: &Pubkey, salt: &[u8; 32]) -> Self {
+        let management = KeyPurpose::Management as u32;
+
+        Self {
+            identity,
+            key: key_address(&identity, &[0u8; 32]),
+            purpose_keys: purpose_keys_address(&identity, management),
+            claim: claim_address(&identity, &Pubkey::default(), 0),
+            claim_index: claim_index_address(&identity, 0),
+            claim_payer: None,
+            index_payer: None,
+            evm_issuer: None,
+            salt: factory_address(&[SALT_SEED, factory.as_ref(), salt]),
+            wallet: factory_address(&[WALLET_SEED, factory.as_ref(), wallet.as_ref()]),
+            identity_wallets: factory_address(&[IDENTITY_WALLETS_SEED, factory.as_ref(), identity.as_ref()]),
+            management_keys: Vec::new(),
+        }
+    }
+}
+
+/// The payer of the claim at `claim`, the second remaining account.
+fn claim_payer(ctx: &Context<LzReceiveTypes>, claim: &Pubkey) -> Result<Pubkey> {
+    let claim_account = ctx.remaining_accounts.get(1).ok_or(identity_lib::Error::ClaimNotFound)?;
+    require_keys_eq!(claim_account.key(), *claim, identity_lib::Error::ClaimNotFound);
+    if claim_account.owner != &identity_lib::ID {
+        return Err(identity_lib::Error::ClaimNotFound.into());
+    }
+
+    let claim = Claim::try_deserialize(&mut &claim_account.try_borrow_data()?[..])?;
+    Ok(claim.payer)
+}
+
+/// The payer of the topic index at `claim_index`, the third remaining account.
+fn index_payer(ctx: &Context<LzReceiveTypes>, claim_index: &Pubkey) -> Result<Pubkey> {
+    let index_account = ctx.remaining_accounts.get(2).ok_or(identity_lib::Error::ClaimNotFound)?;
+    require_keys_eq!(index_account.key(), *claim_index, identity_lib::Error::ClaimNotFound);
+    if index_account.owner != &identity_lib::ID {
+        return Err(identity_lib::Error::ClaimNotFound.into());
+    }
+
+    let claim_index = ClaimTopicIndex::try_deserialize(&mut &index_account.try_borrow_data()?[..])?;
+    Ok(claim_index.payer)
+}
+
+/// The identity the factory deploys for `wallet` and `salt`.
+fn new_identity(factory: &Account<Factory>, wallet: &Pubkey, salt: &[u8; 32]) -> Pubkey {
+    identity_address(&[IDENTITY_SEED, factory.key().as_ref(), wallet.as_ref(), salt])
+}
+
+fn key_address(identity: &Pubkey, key_hash: &[u8; 32]) -> Pubkey {
+    identity_address(&[KEY_SEED, identity.as_ref(), key_hash])
+}
+
+fn purpose_keys_address(identity: &Pubkey, purpose: u32) -> Pubkey {
+    identity_address(&[KEYS_SEED, identity.as_ref(), &purpose.to_le_bytes()])
+}
+
+fn claim_address(identity: &Pubkey, issuer: &Pubkey, topic: u64) -> Pubkey {
+    identity_address(&[CLAIM_SEED, identity.as_ref(), &hash_claim(issuer, topic)])
+}
+
+fn claim_index_address(identity: &Pubkey, topic: u64) -> Pubkey {
+    identity_address(&[CLAIMS_SEED, identity.as_ref(), &topic.to_le_bytes()])
+}
+
+fn identity_address(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &identity_lib::ID).0
+}
+
+fn factory_address(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &crate::ID).0
+}
+
+fn writable(pubkey: Pubkey) -> LzAccount {
+    LzAccount { pubkey, is_signer: false, is_writable: true }
+}
+
+fn readonly(pubkey: Pubkey) -> LzAccount {
+    LzAccount { pubkey, is_signer: false, is_writable: false }
+}
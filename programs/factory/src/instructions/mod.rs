@@ -1,9 +1,17 @@
 pub mod set_remote;
+pub mod quote_send;
+pub mod remove_remote;
+pub mod send;
+pub mod set_enforced_options;
 pub mod lz_receive;
 pub mod lz_receive_types;
 pub mod init_count;
 
 pub use init_count::*;
 pub use set_remote::*;
+pub use quote_send::*;
+pub use remove_remote::*;
+pub use send::*;
+pub use set_enforced_options::*;
 pub use lz_receive::*;
 pub use lz_receive_types::*;
\ No newline at end of file
@@ -0,0 +1,49 @@
+use crate::*;
+use oapp::endpoint::{instructions::QuoteParams, MessagingFee, ID as ENDPOINT_ID};
+
+/// Accounts of the endpoint's `quote` are passed as remaining accounts.
+#[derive(Accounts)]
+#[instruction(params: QuoteSendParams)]
+pub struct QuoteSend<'info> {
+    pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [REMOTE_SEED, factory.key().as_ref(), &params.dst_eid.to_be_bytes()],
+        bump = remote.bump
+    )]
+    pub remote: Account<'info, Remote>,
+    /// Absent for endpoints without enforced options.
+    #[account(
+        seeds = [ENFORCED_OPTIONS_SEED, factory.key().as_ref(), &params.dst_eid.to_be_bytes()],
+        bump = enforced_options.bump
+    )]
+    pub enforced_options: Option<Account<'info, EnforcedOptions>>,
+}
+
+impl QuoteSend<'_> {
+    /// Quotes the fee of sending `method` with `payload` to the factory's peer
+    /// on `params.dst_eid`, encoded the same way as the `send_*` instructions
+    /// and with the options enforced on `method`, if any.
+    pub fn apply(ctx: &Context<QuoteSend>, params: &QuoteSendParams) -> Result<MessagingFee> {
+        oapp::endpoint_cpi::quote(
+            ENDPOINT_ID,
+            ctx.remaining_accounts,
+            QuoteParams {
+                sender: ctx.accounts.factory.key(),
+                dst_eid: params.dst_eid,
+                receiver: ctx.accounts.remote.address,
+                message: encode_message(&params.method, &params.payload),
+                options: message_options(ctx.accounts.enforced_options.as_ref(), &params.method, &params.options)?,
+                pay_in_lz_token: params.pay_in_lz_token,
+            },
+        )
+    }
+}
+
+#[derive(Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct QuoteSendParams {
+    pub dst_eid: u32,
+    pub method: String,
+    pub payload: Vec<u8>,
+    pub options: Vec<u8>,
+    pub pay_in_lz_token: bool,
+}
@@ -0,0 +1,34 @@
+use crate::*;
+
+#[derive(Accounts)]
+#[instruction(params: RemoveRemoteParams)]
+pub struct RemoveRemote<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(constraint = factory.owner == admin.key() @ ErrorCode::Unauthorized)]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [REMOTE_SEED, factory.key().as_ref(), &params.dst_eid.to_be_bytes()],
+        bump = remote.bump
+    )]
+    pub remote: Account<'info, Remote>,
+}
+
+impl RemoveRemote<'_> {
+    pub fn apply(_ctx: &mut Context<RemoveRemote>, params: &RemoveRemoteParams) -> Result<()> {
+        // Like the EVM OApp, a removed peer is set to zero
+        emit!(PeerSet {
+            eid: params.dst_eid,
+            peer: [0u8; 32],
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct RemoveRemoteParams {
+    pub dst_eid: u32,
+}
@@ -0,0 +1,208 @@
+use crate::*;
+use identity_lib::{hash_claim, Claim, CLAIM_SEED};
+use oapp::endpoint::{instructions::SendParams, MessagingReceipt, ID as ENDPOINT_ID};
+
+/// Accounts of the endpoint's `send` are passed as remaining accounts.
+#[derive(Accounts)]
+#[instruction(params: SendMessageParams)]
+pub struct SendWalletLinked<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(constraint = factory.owner == payer.key() @ ErrorCode::Unauthorized)]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [REMOTE_SEED, factory.key().as_ref(), &params.dst_eid.to_be_bytes()],
+        bump = remote.bump
+    )]
+    pub remote: Account<'info, Remote>,
+    /// Absent for endpoints without enforced options.
+    #[account(
+        seeds = [ENFORCED_OPTIONS_SEED, factory.key().as_ref(), &params.dst_eid.to_be_bytes()],
+        bump = enforced_options.bump
+    )]
+    pub enforced_options: Option<Account<'info, EnforcedOptions>>,
+    #[account(constraint = identity_account.factory == factory.key() @ ErrorCode::InvalidIdentity)]
+    pub identity_account: Account<'info, IdentityAccount>,
+    #[account(
+        seeds = [WALLET_SEED, factory.key().as_ref(), wallet_account.wallet.as_ref()],
+        bump = wallet_account.bump,
+        constraint = wallet_account.identity == identity_account.key() @ ErrorCode::WalletNotLinkedToIdentity
+    )]
+    pub wallet_account: Account<'info, WalletIdentity>,
+}
+
+impl SendWalletLinked<'_> {
+    /// Sends `WalletLinked`: `wallet | identity`.
+    pub fn apply(ctx: &mut Context<SendWalletLinked>, params: &SendMessageParams) -> Result<MessagingReceipt> {
+        let wallet_account = &ctx.accounts.wallet_account;
+
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&wallet_account.wallet.to_bytes());
+        payload.extend_from_slice(&wallet_account.identity.to_bytes());
+
+        send_message(
+            &ctx.accounts.factory,
+            &ctx.accounts.remote,
+            ctx.accounts.enforced_options.as_ref(),
+            ctx.remaining_accounts,
+            params,
+            "WalletLinked",
+            &payload,
+        )
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(params: SendMessageParams)]
+pub struct SendAddedKey<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(constraint = factory.owner == payer.key() @ ErrorCode::Unauthorized)]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [REMOTE_SEED, factory.key().as_ref(), &params.dst_eid.to_be_bytes()],
+        bump = remote.bump
+    )]
+    pub remote: Account<'info, Remote>,
+    /// Absent for endpoints without enforced options.
+    #[account(
+        seeds = [ENFORCED_OPTIONS_SEED, factory.key().as_ref(), &params.dst_eid.to_be_bytes()],
+        bump = enforced_options.bump
+    )]
+    pub enforced_options: Option<Account<'info, EnforcedOptions>>,
+    #[account(constraint = identity_account.factory == factory.key() @ ErrorCode::InvalidIdentity)]
+    pub identity_account: Account<'info, IdentityAccount>,
+    #[account(constraint = key_account.identity == identity_account.key() @ ErrorCode::InvalidIdentity)]
+    pub key_account: Account<'info, KeyAccount>,
+}
+
+impl SendAddedKey<'_> {
+    /// Sends `AddedKey`: `wallet | key hash | purposes (u32) | key type (u32)`,
+    /// `purposes` being the key's `KeyPurpose::mask` bitmask.
+    pub fn apply(ctx: &mut Context<SendAddedKey>, params: &SendMessageParams) -> Result<MessagingReceipt> {
+        let key_account = &ctx.accounts.key_account;
+
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&ctx.accounts.identity_account.wallet.to_bytes());
+        payload.extend_from_slice(&key_account.key);
+        payload.extend_from_slice(&key_account.purposes.to_le_bytes());
+        payload.extend_from_slice(&(key_account.key_type as u32).to_le_bytes());
+
+        send_message(
+            &ctx.accounts.factory,
+            &ctx.accounts.remote,
+            ctx.accounts.enforced_options.as_ref(),
+            ctx.remaining_accounts,
+            params,
+            "AddedKey",
+            &payload,
+        )
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(params: SendMessageParams)]
+pub struct SendAddedClaim<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(constraint = factory.owner == payer.key() @ ErrorCode::Unauthorized)]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [REMOTE_SEED, factory.key().as_ref(), &params.dst_eid.to_be_bytes()],
+        bump = remote.bump
+    )]
+    pub remote: Account<'info, Remote>,
+    /// Absent for endpoints without enforced options.
+    #[account(
+        seeds = [ENFORCED_OPTIONS_SEED, factory.key().as_ref(), &params.dst_eid.to_be_bytes()],
+        bump = enforced_options.bump
+    )]
+    pub enforced_options: Option<Account<'info, EnforcedOptions>>,
+    #[account(constraint = identity_account.factory == factory.key() @ ErrorCode::InvalidIdentity)]
+    pub identity_account: Account<'info, IdentityAccount>,
+    #[account(
+        seeds = [CLAIM_SEED, identity_account.key().as_ref(), &hash_claim(&claim.issuer, claim.topic)],
+        bump = claim.bump,
+        seeds::program = identity_lib::ID
+    )]
+    pub claim: Account<'info, Claim>,
+}
+
+impl SendAddedClaim<'_> {
+    /// Sends `AddedClaim` in the layout of an inbound `AddClaim`:
+    /// `wallet | topic | scheme | issuer_wallet | issuer | signature | data | uri`,
+    /// with the signature and data prefixed by their u32 length.
+    pub fn apply(ctx: &mut Context<SendAddedClaim>, params: &SendMessageParams) -> Result<MessagingReceipt> {
+        let claim = &ctx.accounts.claim;
+
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&ctx.accounts.identity_account.wallet.to_bytes());
+        payload.extend_from_slice(&claim.topic.to_le_bytes());
+        payload.extend_from_slice(&claim.scheme.to_le_bytes());
+        payload.extend_from_slice(&claim.issuer_wallet.to_bytes());
+        payload.extend_from_slice(&claim.issuer.to_bytes());
+        payload.extend_from_slice(&(claim.signature.len() as u32).to_le_bytes());
+        payload.extend_from_slice(&claim.signature);
+        payload.extend_from_slice(&(claim.data.len() as u32).to_le_bytes());
+        payload.extend_from_slice(&claim.data);
+        payload.extend_from_slice(claim.uri.as_bytes());
+
+        send_message(
+            &ctx.accounts.factory,
+            &ctx.accounts.remote,
+            ctx.accounts.enforced_options.as_ref(),
+            ctx.remaining_accounts,
+            params,
+            "AddedClaim",
+            &payload,
+        )
+    }
+}
+
+/// Sends `method` with `payload` to the factory's peer on `params.dst_eid`
+/// through the endpoint, signing as the factory PDA. The options enforced on
+/// `method`, if any, are combined with the caller's.
+fn send_message(
+    factory: &Account<Factory>,
+    remote: &Account<Remote>,
+    enforced_options: Option<&Account<EnforcedOptions>>,
+    endpoint_accounts: &[AccountInfo],
+    params: &SendMessageParams,
+    method_name: &str,
+    payload: &[u8],
+) -> Result<MessagingReceipt> {
+    let seeds: &[&[u8]] = &[COUNT_SEED, &[factory.id], &[factory.bump]];
+    oapp::endpoint_cpi::send(
+        ENDPOINT_ID,
+        factory.key(),
+        endpoint_accounts,
+        seeds,
+        SendParams {
+            dst_eid: params.dst_eid,
+            receiver: remote.address,
+            message: encode_message(method_name, payload),
+            options: message_options(enforced_options, method_name, &params.options)?,
+            native_fee: params.native_fee,
+            lz_token_fee: params.lz_token_fee,
+        },
+    )
+}
+
+/// Encodes a message like the ones `lz_receive` decodes: the method name's
+/// length (u8), the method name, then the payload.
+pub fn encode_message(method_name: &str, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(1 + method_name.len() + payload.len());
+    message.push(method_name.len() as u8);
+    message.extend_from_slice(method_name.as_bytes());
+    message.extend_from_slice(payload);
+
+    message
+}
+
+#[derive(Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct SendMessageParams {
+    pub dst_eid: u32,
+    pub options: Vec<u8>,
+    pub native_fee: u64,
+    pub lz_token_fee: u64,
+}
@@ -0,0 +1,91 @@
+use crate::*;
+use oapp::options::{assert_type_3, combine_options};
+
+/// Maximum length of the enforced options of one outbound method.
+pub const MAX_OPTIONS_LEN: usize = 128;
+
+/// Type-3 executor options enforced on each outbound method sent to one
+/// destination endpoint.
+#[account]
+pub struct EnforcedOptions {
+    pub wallet_linked: Vec<u8>,
+    pub added_key: Vec<u8>,
+    pub added_claim: Vec<u8>,
+    pub bump: u8,
+}
+
+impl EnforcedOptions {
+    pub const SIZE: usize = 8 + (4 + MAX_OPTIONS_LEN) * 3 + 1;
+
+    /// Combines the options enforced on `method` with the caller's `extra_options`.
+    pub fn combine(&self, method: &str, extra_options: &Vec<u8>) -> Result<Vec<u8>> {
+        let enforced_options = match method {
+            "WalletLinked" => &self.wallet_linked,
+            "AddedKey" => &self.added_key,
+            "AddedClaim" => &self.added_claim,
+            _ => return err!(ErrorCode::UnknownMethod),
+        };
+
+        combine_options(enforced_options.clone(), extra_options)
+    }
+}
+
+/// The options of a `method` message: the caller's `extra_options`, combined
+/// with those enforced on `method` when options are enforced on its endpoint.
+pub fn message_options(
+    enforced_options: Option<&Account<EnforcedOptions>>,
+    method: &str,
+    extra_options: &Vec<u8>,
+) -> Result<Vec<u8>> {
+    match enforced_options {
+        Some(enforced_options) => enforced_options.combine(method, extra_options),
+        None => Ok(extra_options.clone()),
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(params: SetEnforcedOptionsParams)]
+pub struct SetEnforcedOptions<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(constraint = factory.owner == admin.key() @ ErrorCode::Unauthorized)]
+    pub factory: Account<'info, Factory>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = EnforcedOptions::SIZE,
+        seeds = [ENFORCED_OPTIONS_SEED, factory.key().as_ref(), &params.dst_eid.to_be_bytes()],
+        bump
+    )]
+    pub enforced_options: Account<'info, EnforcedOptions>,
+    pub system_program: Program<'info, System>,
+}
+
+impl SetEnforcedOptions<'_> {
+    pub fn apply(ctx: &mut Context<SetEnforcedOptions>, params: &SetEnforcedOptionsParams) -> Result<()> {
+        for options in [&params.wallet_linked, &params.added_key, &params.added_claim] {
+            require!(options.len() <= MAX_OPTIONS_LEN, ErrorCode::OptionsTooLong);
+            if !options.is_empty() {
+                assert_type_3(options)?;
+            }
+        }
+
+        let enforced_options = &mut ctx.accounts.enforced_options;
+        enforced_options.wallet_linked = params.wallet_linked.clone();
+        enforced_options.added_key = params.added_key.clone();
+        enforced_options.added_claim = params.added_claim.clone();
+        enforced_options.bump = ctx.bumps.enforced_options;
+
+        emit!(EnforcedOptionsSet { eid: params.dst_eid });
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct SetEnforcedOptionsParams {
+    pub dst_eid: u32,
+    pub wallet_linked: Vec<u8>,
+    pub added_key: Vec<u8>,
+    pub added_claim: Vec<u8>,
+}
@@ -15,11 +15,13 @@ impl Remote {
 pub struct SetRemote<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
+    #[account(constraint = factory.owner == admin.key() @ ErrorCode::Unauthorized)]
+    pub factory: Account<'info, Factory>,
     #[account(
         init_if_needed,
         payer = admin,
         space = Remote::SIZE,
-        seeds = [b"remote", admin.key().as_ref()],
+        seeds = [REMOTE_SEED, factory.key().as_ref(), &params.dst_eid.to_be_bytes()],
         bump
     )]
     pub remote: Account<'info, Remote>,
@@ -29,6 +31,13 @@ pub struct SetRemote<'info> {
 impl SetRemote<'_> {
     pub fn apply(ctx: &mut Context<SetRemote>, params: &SetRemoteParams) -> Result<()> {
         ctx.accounts.remote.address = params.remote;
+        ctx.accounts.remote.bump = ctx.bumps.remote;
+
+        emit!(PeerSet {
+            eid: params.dst_eid,
+            peer: params.remote,
+        });
+
         Ok(())
     }
 }
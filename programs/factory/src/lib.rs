@@ -1,20 +1,27 @@
 use std::io::Read;
 use byteorder::{ReadBytesExt, LittleEndian};
 
-use anchor_lang::{prelude::*, solana_program, Result, require};
-use solana_program::{instruction::{Instruction, AccountMeta}, program::invoke_signed, pubkey::Pubkey};
-use identity_lib::program::Identity;
+use anchor_lang::{prelude::*, solana_program, system_program, Result, require};
+use anchor_spl::token_interface::Mint;
+use identity_lib::{hash_key, program::Identity, IdentityAccount, KeyAccount, KeyPurpose, KeyType, ManagementKey, KEY_SEED};
 use oapp::LzReceiveParams;
 
 pub mod errors;
 pub mod state;
 pub mod instructions;
-mod msg_codec;
 
 use crate::instructions::*;
 use state::*;
 
 pub const LZ_RECEIVE_TYPES_SEED: &[u8] = oapp::LZ_RECEIVE_TYPES_SEED;
+pub const COUNT_SEED: &[u8] = b"Count";
+pub const REMOTE_SEED: &[u8] = b"Remote";
+pub const ENFORCED_OPTIONS_SEED: &[u8] = b"EnforcedOptions";
+pub const TOKEN_FACTORY_SEED: &[u8] = b"token_factory";
+pub const TOKEN_SEED: &[u8] = b"token";
+pub const SALT_SEED: &[u8] = b"salt";
+pub const WALLET_SEED: &[u8] = b"wallet";
+pub const IDENTITY_WALLETS_SEED: &[u8] = b"identity_wallets";
 
 declare_id!("EjTQazH7zvwvBFDkbJRnpvQfjuQBqjHTdbYE25iaxZoJ");
 
@@ -26,6 +33,17 @@ pub mod factory_contract {
         SetRemote::apply(&mut ctx, &params)
     }
 
+    pub fn remove_remote(mut ctx: Context<RemoveRemote>, params: RemoveRemoteParams) -> Result<()> {
+        RemoveRemote::apply(&mut ctx, &params)
+    }
+
+    pub fn set_enforced_options(
+        mut ctx: Context<SetEnforcedOptions>,
+        params: SetEnforcedOptionsParams,
+    ) -> Result<()> {
+        SetEnforcedOptions::apply(&mut ctx, &params)
+    }
+
     pub fn init_count(mut ctx: Context<InitCount>, params: InitCountParams) -> Result<()> {
         InitCount::apply(&mut ctx, &params)
     }
@@ -51,124 +69,253 @@ pub mod factory_contract {
         Ok(factory.initialized)
     }
 
+    /// `IdFactory.isSaltTaken`.
+    pub fn is_salt_taken(ctx: Context<IsSaltTaken>, _salt: [u8; 32]) -> Result<bool> {
+        Ok(ctx.accounts.salt_account.owner == &crate::ID)
+    }
+
     pub fn create_identity(
-        ctx: Context<LzReceive>,
+        ctx: Context<CreateIdentity>,
         wallet: Pubkey,
         salt: [u8; 32],
         initial_management_key: Pubkey,
     ) -> Result<()> {
         require!(*ctx.accounts.payer.key == ctx.accounts.factory.owner, ErrorCode::Unauthorized);
-    
-        let seeds = &[b"identity", wallet.as_ref(), salt.as_ref()];
-        let (identity_address, bump) = Pubkey::find_program_address(seeds, ctx.program_id);
-    
-        let mut data = initial_management_key.to_bytes().to_vec();
-        data.extend(ctx.accounts.factory.key().to_bytes());
-    
-        let instruction = Instruction {
-            program_id: ctx.accounts.identity_program.key(),
-            accounts: vec![
-                AccountMeta::new(identity_address, false),
-                AccountMeta::new(*ctx.accounts.payer.key, true),
-                AccountMeta::new(ctx.accounts.factory.key(), false),
-                AccountMeta::new_readonly(*ctx.accounts.system_program.key, false),
-            ],
-            data,
+
+        let accounts = identity_lib::cpi::accounts::Initialize {
+            identity_account: ctx.accounts.identity_account.to_account_info(),
+            management_key: ctx.accounts.management_key_account.to_account_info(),
+            management_keys: ctx.accounts.management_keys_account.to_account_info(),
+            user: ctx.accounts.payer.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            factory: ctx.accounts.factory.to_account_info(),
         };
-    
-        let signers_seeds: &[&[&[u8]]] = &[&[b"identity", wallet.as_ref(), salt.as_ref(), &[bump]]];
-    
-        invoke_signed(
-            &instruction,
-            &[
-                ctx.accounts.factory.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
+
+        let new_accounts = NewIdentityAccounts {
+            salt: ctx.accounts.salt_account.to_account_info(),
+            wallet: ctx.accounts.wallet_account.to_account_info(),
+            identity_wallets: ctx.accounts.identity_wallets_account.to_account_info(),
+        };
+
+        deploy_identity(
+            &ctx.accounts.factory,
+            ctx.accounts.identity_program.to_account_info(),
+            accounts,
+            new_accounts,
+            wallet,
+            salt,
+            initial_management_key,
+        )
+    }
+
+    /// `IdFactory.createIdentityWithManagementKeys`: like `create_identity`, with
+    /// `management_keys` instead of a single key. Their `[KEY_SEED, identity, key_hash]`
+    /// PDAs are the remaining accounts, in order.
+    pub fn create_identity_with_management_keys<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateIdentityWithManagementKeys<'info>>,
+        wallet: Pubkey,
+        salt: [u8; 32],
+        management_keys: Vec<ManagementKey>,
+    ) -> Result<()> {
+        require!(*ctx.accounts.payer.key == ctx.accounts.factory.owner, ErrorCode::Unauthorized);
+
+        let accounts = identity_lib::cpi::accounts::InitializeWithManagementKeys {
+            identity_account: ctx.accounts.identity_account.to_account_info(),
+            management_keys: ctx.accounts.management_keys_account.to_account_info(),
+            user: ctx.accounts.payer.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            factory: ctx.accounts.factory.to_account_info(),
+        };
+
+        let new_accounts = NewIdentityAccounts {
+            salt: ctx.accounts.salt_account.to_account_info(),
+            wallet: ctx.accounts.wallet_account.to_account_info(),
+            identity_wallets: ctx.accounts.identity_wallets_account.to_account_info(),
+        };
+
+        deploy_identity_with_management_keys(
+            &ctx.accounts.factory,
+            ctx.accounts.identity_program.to_account_info(),
+            accounts,
+            ctx.remaining_accounts,
+            new_accounts,
+            wallet,
+            salt,
+            management_keys,
+        )
+    }
+
+    /// `IdFactory.addTokenFactory`: allows `token_factory` to create token identities.
+    pub fn add_token_factory(ctx: Context<AddTokenFactory>, token_factory: Pubkey) -> Result<()> {
+        require!(*ctx.accounts.payer.key == ctx.accounts.factory.owner, ErrorCode::Unauthorized);
+
+        ctx.accounts.token_factory_account.set_inner(TokenFactory {
+            bump: ctx.bumps.token_factory_account,
+            token_factory,
+        });
+
+        msg!("Token factory added: {:?}", token_factory);
+        emit!(TokenFactoryAdded { token_factory });
+
+        Ok(())
+    }
+
+    /// `IdFactory.removeTokenFactory`.
+    pub fn remove_token_factory(ctx: Context<RemoveTokenFactory>, token_factory: Pubkey) -> Result<()> {
+        require!(*ctx.accounts.payer.key == ctx.accounts.factory.owner, ErrorCode::Unauthorized);
+
+        msg!("Token factory removed: {:?}", token_factory);
+        emit!(TokenFactoryRemoved { token_factory });
+
+        Ok(())
+    }
+
+    /// Records the Claim keys of the EVM claim issuer `issuer`, which sign the
+    /// claims bridged to this factory's identities.
+    pub fn set_evm_issuer(ctx: Context<SetEvmIssuer>, issuer: Pubkey, claim_keys: Vec<[u8; 20]>) -> Result<()> {
+        require!(*ctx.accounts.payer.key == ctx.accounts.factory.owner, ErrorCode::Unauthorized);
+
+        let factory = &ctx.accounts.factory;
+        let seeds: &[&[u8]] = &[COUNT_SEED, &[factory.id], &[factory.bump]];
+        identity_lib::cpi::set_evm_issuer(
+            CpiContext::new_with_signer(
                 ctx.accounts.identity_program.to_account_info(),
-            ],
-            signers_seeds,
+                identity_lib::cpi::accounts::SetEvmIssuer {
+                    evm_issuer: ctx.accounts.evm_issuer.to_account_info(),
+                    factory: factory.to_account_info(),
+                    payer: ctx.accounts.payer.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[seeds],
+            ),
+            issuer,
+            claim_keys,
+        )
+    }
+
+    /// `IdFactory.createTokenIdentity`: deploys the identity of the `token` mint,
+    /// managed by `owner`. Only token factories can do this, once per token.
+    pub fn create_token_identity(
+        ctx: Context<CreateTokenIdentity>,
+        token: Pubkey,
+        owner: Pubkey,
+        salt: [u8; 32],
+    ) -> Result<()> {
+        let accounts = identity_lib::cpi::accounts::Initialize {
+            identity_account: ctx.accounts.identity_account.to_account_info(),
+            management_key: ctx.accounts.management_key_account.to_account_info(),
+            management_keys: ctx.accounts.management_keys_account.to_account_info(),
+            user: ctx.accounts.payer.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            factory: ctx.accounts.factory.to_account_info(),
+        };
+        let identity = ctx.accounts.identity_account.key();
+
+        take_salt(
+            &ctx.accounts.salt_account,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.factory.key(),
+            salt,
+            identity,
         )?;
-    
-        ctx.accounts.factory.wallet_to_identity.push((wallet, identity_address));
-        ctx.accounts.factory.linked_wallets.push(wallet);
-        ctx.accounts.factory.identity_addresses.push(identity_address);
-        msg!("Identity created with address: {:?}", identity_address);
-        emit!(IdentityCreated {
-            wallet,
-            identity_address,
+        initialize_identity(
+            &ctx.accounts.factory,
+            ctx.accounts.identity_program.to_account_info(),
+            accounts,
+            token,
+            salt,
+            owner,
+        )?;
+
+        ctx.accounts.token_identity.set_inner(TokenIdentity {
+            bump: ctx.bumps.token_identity,
+            token,
+            identity,
         });
-    
+
+        msg!("Token {:?} linked with identity {:?}", token, identity);
+        emit!(TokenLinked { token, identity });
+
         Ok(())
     }
 
+    /// `IdFactory.getToken`: the token whose identity is `identity_account`.
+    pub fn get_token(ctx: Context<GetToken>) -> Result<Pubkey> {
+        Ok(ctx.accounts.token_identity.token)
+    }
+
+    /// Links `wallet` to `identity`. Besides the factory owner, a wallet already
+    /// linked to the identity or a management key of the identity can do this,
+    /// the latter only if the identity's management threshold is one.
     pub fn link_wallet(
-        ctx: Context<LinkedWallets>,
+        ctx: Context<LinkWallet>,
         wallet: Pubkey,
         identity: Pubkey,
     ) -> Result<()> {
-        let factory_account = &mut ctx.accounts.factory;
+        let accounts = &ctx.accounts;
+        let is_owner = accounts.signer.key == &accounts.factory.owner;
+        let is_linked_wallet = accounts
+            .signer_wallet_account
+            .as_ref()
+            .is_some_and(|w| w.identity == identity);
+        let is_manager = accounts
+            .signer_key
+            .as_ref()
+            .is_some_and(|k| k.identity == identity && k.has_purpose(KeyPurpose::Management));
+        require!(is_owner || is_linked_wallet || is_manager, ErrorCode::Unauthorized);
+        // A management key only acts alone for identities needing a single approval
+        if !is_owner && !is_linked_wallet {
+            require!(accounts.identity_account.management_threshold <= 1, ErrorCode::ManagementThresholdNotMet);
+        }
 
-        require!(ctx.accounts.signer.key == &factory_account.owner, ErrorCode::Unauthorized);
+        add_wallet(
+            &ctx.accounts.factory.key(),
+            &ctx.accounts.wallet_account,
+            &ctx.accounts.identity_wallets_account,
+            &ctx.accounts.signer,
+            &ctx.accounts.system_program,
+            wallet,
+            identity,
+        )?;
 
-        if !factory_account.linked_wallets.contains(&wallet) {
-            factory_account.linked_wallets.push(wallet);
-            factory_account.wallet_to_identity.push((wallet, identity));
-            msg!("Wallet {:?} linked with identity {:?}", wallet, identity);
-            emit!(WalletLinked {
-                wallet,
-                identity,
-            });
-        } else {
-            msg!("Wallet is already linked to this identity.");
-        }
+        msg!("Wallet {:?} linked with identity {:?}", wallet, identity);
+        emit!(WalletLinked {
+            wallet,
+            identity,
+        });
 
         Ok(())
     }
 
+    /// Unlinks `wallet` from `identity`, which must keep at least one wallet.
     pub fn unlink_wallet(
-        ctx: Context<LinkedWallets>,
+        ctx: Context<UnlinkWallet>,
         wallet: Pubkey,
         identity: Pubkey,
     ) -> Result<()> {
-        let factory_account = &mut ctx.accounts.factory;
+        require!(ctx.accounts.signer.key == &ctx.accounts.factory.owner, ErrorCode::Unauthorized);
+        require_keys_eq!(ctx.accounts.wallet_account.identity, identity, ErrorCode::WalletNotLinkedToIdentity);
 
-        require!(ctx.accounts.signer.key == &factory_account.owner, ErrorCode::Unauthorized);
+        let identity_wallets = &mut ctx.accounts.identity_wallets_account;
+        require!(identity_wallets.wallets.len() > 1, ErrorCode::LastWallet);
+        identity_wallets.wallets.retain(|w| *w != wallet);
 
-        if let Some(index) = factory_account.linked_wallets.iter().position(|&x| x == wallet) {
-            factory_account.linked_wallets.remove(index);
-            if let Some(pos) = factory_account.wallet_to_identity.iter().position(|&(w, _)| w == wallet) {
-                factory_account.wallet_to_identity.remove(pos);
-            }
-            msg!("Wallet {:?} unlinked from identity {:?}", wallet, identity);
-            emit!(WalletUnlinked {
-                wallet,
-                identity,
-            });
-        } else {
-            msg!("Wallet not linked to this identity.");
-        }
+        msg!("Wallet {:?} unlinked from identity {:?}", wallet, identity);
+        emit!(WalletUnlinked {
+            wallet,
+            identity,
+        });
 
         Ok(())
     }
-    
-    pub fn get_wallets(ctx: Context<LinkedWallets>, identity: Pubkey) -> Result<Vec<Pubkey>> {
-        let factory_account = &ctx.accounts.factory;
 
-        let wallets: Vec<Pubkey> = factory_account
-            .linked_wallets
-            .iter()
-            .cloned()
-            .filter(|&linked_wallet| factory_account.wallet_to_identity.iter().any(|&(w, i)| w == linked_wallet && i == identity))
-            .collect();
-
-        Ok(wallets)
+    pub fn get_wallets(ctx: Context<GetWallets>, _identity: Pubkey) -> Result<Vec<Pubkey>> {
+        Ok(ctx.accounts.identity_wallets_account.wallets.clone())
     }
 
-    pub fn get_identity(ctx: Context<LinkedWallets>, wallet: Pubkey) -> Result<Pubkey> {
-        if let Some((_, identity)) = ctx.accounts.factory.wallet_to_identity.iter().find(|&&(w, _)| w == wallet) {
-            Ok(*identity)
-        } else {
-            Err(ErrorCode::WalletNotLinked.into())
-        }
+    pub fn get_identity(ctx: Context<GetIdentity>, _wallet: Pubkey) -> Result<Pubkey> {
+        Ok(ctx.accounts.wallet_account.identity)
     }
 
     pub fn get_owner(ctx: Context<LinkedWallets>) -> Result<Pubkey> {
@@ -189,10 +336,38 @@ pub mod factory_contract {
         Ok(())
     }
 
-    pub fn lz_receive(mut ctx: Context<LzReceive>, params: LzReceiveParams) -> Result<()> {
-        LzReceive::apply(ctx, &params)
+    pub fn lz_receive<'info>(mut ctx: Context<'_, '_, 'info, 'info, LzReceive<'info>>, params: LzReceiveParams) -> Result<()> {
+        LzReceive::apply(&mut ctx, &params)
+    }
+
+
+    pub fn send_wallet_linked(
+        mut ctx: Context<SendWalletLinked>,
+        params: SendMessageParams,
+    ) -> Result<oapp::endpoint::MessagingReceipt> {
+        SendWalletLinked::apply(&mut ctx, &params)
+    }
+
+    pub fn send_added_key(
+        mut ctx: Context<SendAddedKey>,
+        params: SendMessageParams,
+    ) -> Result<oapp::endpoint::MessagingReceipt> {
+        SendAddedKey::apply(&mut ctx, &params)
     }
 
+    pub fn send_added_claim(
+        mut ctx: Context<SendAddedClaim>,
+        params: SendMessageParams,
+    ) -> Result<oapp::endpoint::MessagingReceipt> {
+        SendAddedClaim::apply(&mut ctx, &params)
+    }
+
+    pub fn quote_send(
+        ctx: Context<QuoteSend>,
+        params: QuoteSendParams,
+    ) -> Result<oapp::endpoint::MessagingFee> {
+        QuoteSend::apply(&ctx, &params)
+    }
 
     pub fn lz_receive_types(
         ctx: Context<LzReceiveTypes>,
@@ -203,50 +378,355 @@ pub mod factory_contract {
 
 }
 
+/// Factory PDAs created along with a new identity: its salt's marker, its
+/// wallet's link, and its wallet list.
+struct NewIdentityAccounts<'info> {
+    salt: AccountInfo<'info>,
+    wallet: AccountInfo<'info>,
+    identity_wallets: AccountInfo<'info>,
+}
+
+/// Deploys an identity through `identity::initialize`, signing as the factory PDA,
+/// and links it to `wallet`. Each salt deploys a single identity.
+fn deploy_identity<'info>(
+    factory: &Account<'info, Factory>,
+    identity_program: AccountInfo<'info>,
+    accounts: identity_lib::cpi::accounts::Initialize<'info>,
+    new_accounts: NewIdentityAccounts<'info>,
+    wallet: Pubkey,
+    salt: [u8; 32],
+    initial_management_key: Pubkey,
+) -> Result<()> {
+    let identity_address = accounts.identity_account.key();
+    let payer = accounts.user.clone();
+    let system_program = accounts.system_program.clone();
+
+    take_salt(&new_accounts.salt, &payer, &system_program, &factory.key(), salt, identity_address)?;
+    initialize_identity(factory, identity_program, accounts, wallet, salt, initial_management_key)?;
+
+    record_identity(&factory.key(), &new_accounts, &payer, &system_program, wallet, identity_address, salt)
+}
+
+/// `deploy_identity` through `identity::initialize_with_management_keys`, with
+/// `key_accounts` as the PDAs of `management_keys`.
+#[allow(clippy::too_many_arguments)]
+fn deploy_identity_with_management_keys<'info>(
+    factory: &Account<'info, Factory>,
+    identity_program: AccountInfo<'info>,
+    accounts: identity_lib::cpi::accounts::InitializeWithManagementKeys<'info>,
+    key_accounts: &[AccountInfo<'info>],
+    new_accounts: NewIdentityAccounts<'info>,
+    wallet: Pubkey,
+    salt: [u8; 32],
+    management_keys: Vec<ManagementKey>,
+) -> Result<()> {
+    let identity_address = accounts.identity_account.key();
+    let payer = accounts.user.clone();
+    let system_program = accounts.system_program.clone();
+
+    take_salt(&new_accounts.salt, &payer, &system_program, &factory.key(), salt, identity_address)?;
+
+    let seeds: &[&[u8]] = &[COUNT_SEED, &[factory.id], &[factory.bump]];
+    identity_lib::cpi::initialize_with_management_keys(
+        CpiContext::new_with_signer(identity_program, accounts, &[seeds])
+            .with_remaining_accounts(key_accounts.to_vec()),
+        wallet,
+        salt,
+        management_keys,
+    )?;
+
+    record_identity(&factory.key(), &new_accounts, &payer, &system_program, wallet, identity_address, salt)
+}
+
+/// Links a newly deployed identity to `wallet`.
+fn record_identity<'info>(
+    factory: &Pubkey,
+    new_accounts: &NewIdentityAccounts<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    wallet: Pubkey,
+    identity_address: Pubkey,
+    salt: [u8; 32],
+) -> Result<()> {
+    add_wallet(
+        factory,
+        &new_accounts.wallet,
+        &new_accounts.identity_wallets,
+        payer,
+        system_program,
+        wallet,
+        identity_address,
+    )?;
+    msg!("Identity created with address: {:?}", identity_address);
+    emit!(IdentityCreated {
+        wallet,
+        identity_address,
+        salt,
+    });
+
+    Ok(())
+}
+
+/// Creates the `[SALT_SEED, factory, salt]` marker of `identity`, failing if
+/// the factory already used the salt.
+fn take_salt<'info>(
+    salt_account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    factory: &Pubkey,
+    salt: [u8; 32],
+    identity: Pubkey,
+) -> Result<()> {
+    let (salt_address, bump) = Pubkey::find_program_address(&[SALT_SEED, factory.as_ref(), &salt], &crate::ID);
+    require_keys_eq!(salt_account.key(), salt_address, ErrorCode::InvalidSaltAccount);
+    require!(salt_account.owner != &crate::ID, ErrorCode::SaltTaken);
+
+    create_pda_account(
+        salt_account,
+        payer,
+        system_program,
+        &[SALT_SEED, factory.as_ref(), &salt, &[bump]],
+        Salt::LEN,
+        &Salt { bump, identity },
+    )
+}
+
+/// Links `wallet` to `identity` through its `[WALLET_SEED, factory, wallet]`
+/// PDA and appends it to the identity's `[IDENTITY_WALLETS_SEED, factory, identity]`
+/// wallet list, creating the list for the identity's first wallet. Each factory
+/// keeps its own links, so that one factory can't claim another's wallets.
+fn add_wallet<'info>(
+    factory: &Pubkey,
+    wallet_account: &AccountInfo<'info>,
+    identity_wallets_account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    wallet: Pubkey,
+    identity: Pubkey,
+) -> Result<()> {
+    let (wallet_address, wallet_bump) =
+        Pubkey::find_program_address(&[WALLET_SEED, factory.as_ref(), wallet.as_ref()], &crate::ID);
+    require_keys_eq!(wallet_account.key(), wallet_address, ErrorCode::InvalidWalletAccount);
+    require!(wallet_account.owner != &crate::ID, ErrorCode::WalletAlreadyLinked);
+
+    create_pda_account(
+        wallet_account,
+        payer,
+        system_program,
+        &[WALLET_SEED, factory.as_ref(), wallet.as_ref(), &[wallet_bump]],
+        WalletIdentity::LEN,
+        &WalletIdentity { bump: wallet_bump, wallet, identity },
+    )?;
+
+    let (wallets_address, wallets_bump) =
+        Pubkey::find_program_address(&[IDENTITY_WALLETS_SEED, factory.as_ref(), identity.as_ref()], &crate::ID);
+    require_keys_eq!(identity_wallets_account.key(), wallets_address, ErrorCode::InvalidWalletAccount);
+
+    if identity_wallets_account.owner != &crate::ID {
+        return create_pda_account(
+            identity_wallets_account,
+            payer,
+            system_program,
+            &[IDENTITY_WALLETS_SEED, factory.as_ref(), identity.as_ref(), &[wallets_bump]],
+            IdentityWallets::LEN,
+            &IdentityWallets { bump: wallets_bump, identity, wallets: vec![wallet] },
+        );
+    }
+
+    let mut data = identity_wallets_account.try_borrow_mut_data()?;
+    let mut identity_wallets = IdentityWallets::try_deserialize(&mut &data[..])?;
+    require!(identity_wallets.wallets.len() < IdentityWallets::MAX_WALLETS, ErrorCode::TooManyWallets);
+    identity_wallets.wallets.push(wallet);
+    identity_wallets.try_serialize(&mut &mut data[..])
+}
+
+/// Creates the factory-owned PDA signed for by `seeds` at `account`, holding `data`.
+fn create_pda_account<'info, T: AccountSerialize>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    space: usize,
+    data: &T,
+) -> Result<()> {
+    // Like Anchor's `init`, an address already holding lamports is topped up,
+    // allocated and assigned instead, so that funding it can't block its creation
+    let rent = Rent::get()?.minimum_balance(space);
+    if account.lamports() == 0 {
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                &[seeds],
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        )?;
+    } else {
+        let top_up = rent.saturating_sub(account.lamports());
+        if top_up > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    system_program::Transfer {
+                        from: payer.clone(),
+                        to: account.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::Allocate { account_to_allocate: account.clone() },
+                &[seeds],
+            ),
+            space as u64,
+        )?;
+        system_program::assign(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::Assign { account_to_assign: account.clone() },
+                &[seeds],
+            ),
+            &crate::ID,
+        )?;
+    }
+
+    data.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])
+}
+
+/// The identity `wallet_account`, the `[WALLET_SEED, factory, wallet]` PDA,
+/// links `wallet` to in `factory`.
+pub fn linked_identity(wallet_account: &AccountInfo, factory: &Pubkey, wallet: &Pubkey) -> Result<Pubkey> {
+    let (wallet_address, _) =
+        Pubkey::find_program_address(&[WALLET_SEED, factory.as_ref(), wallet.as_ref()], &crate::ID);
+    require_keys_eq!(wallet_account.key(), wallet_address, ErrorCode::InvalidWalletAccount);
+    if wallet_account.owner != &crate::ID {
+        return Err(ErrorCode::WalletNotLinked.into());
+    }
+
+    let wallet_identity = WalletIdentity::try_deserialize(&mut &wallet_account.try_borrow_data()?[..])?;
+    Ok(wallet_identity.identity)
+}
+
+/// Calls `identity::initialize`, signing as the factory PDA. The identity PDA is
+/// derived from `wallet`, which is the mint for token identities.
+fn initialize_identity<'info>(
+    factory: &Account<'info, Factory>,
+    identity_program: AccountInfo<'info>,
+    accounts: identity_lib::cpi::accounts::Initialize<'info>,
+    wallet: Pubkey,
+    salt: [u8; 32],
+    initial_management_key: Pubkey,
+) -> Result<()> {
+    let seeds: &[&[u8]] = &[COUNT_SEED, &[factory.id], &[factory.bump]];
+    identity_lib::cpi::initialize(
+        CpiContext::new_with_signer(identity_program, accounts, &[seeds]),
+        wallet,
+        salt,
+        initial_management_key,
+    )
+}
+
+fn new_identity_accounts<'info>(accounts: &LzReceive<'info>) -> NewIdentityAccounts<'info> {
+    NewIdentityAccounts {
+        salt: accounts.salt_account.to_account_info(),
+        wallet: accounts.wallet_account.to_account_info(),
+        identity_wallets: accounts.identity_wallets_account.to_account_info(),
+    }
+}
+
+fn create_identity(
+    accounts: &LzReceive,
+    wallet: Pubkey,
+    salt: [u8; 32],
+    initial_management_key: Pubkey,
+) -> Result<()> {
+    let identity_accounts = identity_lib::cpi::accounts::Initialize {
+        identity_account: accounts.identity_account.to_account_info(),
+        management_key: accounts.key_account.to_account_info(),
+        management_keys: accounts.purpose_keys_account.to_account_info(),
+        user: accounts.payer.to_account_info(),
+        system_program: accounts.system_program.to_account_info(),
+        factory: accounts.factory.to_account_info(),
+    };
+
+    deploy_identity(
+        &accounts.factory,
+        accounts.identity_program.to_account_info(),
+        identity_accounts,
+        new_identity_accounts(accounts),
+        wallet,
+        salt,
+        initial_management_key,
+    )
+}
+
+fn create_identity_with_management_keys<'info>(
+    accounts: &LzReceive<'info>,
+    key_accounts: &[AccountInfo<'info>],
+    wallet: Pubkey,
+    salt: [u8; 32],
+    management_keys: Vec<ManagementKey>,
+) -> Result<()> {
+    let identity_accounts = identity_lib::cpi::accounts::InitializeWithManagementKeys {
+        identity_account: accounts.identity_account.to_account_info(),
+        management_keys: accounts.purpose_keys_account.to_account_info(),
+        user: accounts.payer.to_account_info(),
+        system_program: accounts.system_program.to_account_info(),
+        factory: accounts.factory.to_account_info(),
+    };
+
+    deploy_identity_with_management_keys(
+        &accounts.factory,
+        accounts.identity_program.to_account_info(),
+        identity_accounts,
+        key_accounts,
+        new_identity_accounts(accounts),
+        wallet,
+        salt,
+        management_keys,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
 fn add_claim(
-    ctx: Context<LzReceive>,
+    accounts: &LzReceive,
     wallet: Pubkey,
     topic: u64,
     scheme: u64,
     issuer_wallet: Pubkey,
-    signature: [u8; 64],
+    issuer: Pubkey,
+    signature: Vec<u8>,
     data: Vec<u8>,
     uri: String,
 ) -> Result<()> {
-    let identity_address = find_identity_address(&ctx, wallet)?;
-
-    let issuer = find_identity_address(&ctx, wallet)?;
-
-    let instruction = Instruction {
-        program_id: issuer, // Assuming you still want to instruct on the identity program
-        accounts: vec![
-            AccountMeta::new(identity_address, false),
-            AccountMeta::new(ctx.accounts.factory.owner, true),
-            AccountMeta::new(ctx.accounts.claims_account.key(), false),
-        ],
-        data: {
-            let mut data_vec = vec![]; 
-            data_vec.extend_from_slice(&topic.to_le_bytes());
-            data_vec.extend_from_slice(&scheme.to_le_bytes());
-            data_vec.extend_from_slice(&issuer_wallet.to_bytes());
-            data_vec.extend_from_slice(&issuer.to_bytes()); // Use identity contract as issuer
-            data_vec.extend_from_slice(&signature);
-            data_vec.extend_from_slice(&(data.len() as u32).to_le_bytes());
-            data_vec.extend_from_slice(&data); 
-            data_vec.extend_from_slice(&(uri.len() as u32).to_le_bytes());
-            data_vec.extend_from_slice(uri.as_bytes());
-            data_vec
-        },
-    };
-
-    invoke_signed(
-        &instruction,
-        &[
-            ctx.accounts.factory.to_account_info(),
-            ctx.accounts.identity_program.to_account_info(),
-            ctx.accounts.claims_account.to_account_info(),
-        ],
-        &[],
+    find_identity_address(accounts, wallet)?;
+
+    let seeds: &[&[u8]] = &[COUNT_SEED, &[accounts.factory.id], &[accounts.factory.bump]];
+    identity_lib::cpi::add_claim(
+        CpiContext::new_with_signer(
+            accounts.identity_program.to_account_info(),
+            claim_context(accounts),
+            &[seeds],
+        ),
+        topic,
+        scheme,
+        issuer_wallet,
+        issuer,
+        signature,
+        data,
+        uri.clone(),
+        // Claims signed by EVM claim issuers carry no validity window
+        0,
+        0,
+        None,
     )?;
 
     msg!("Claim added to identity for wallet: {:?}", wallet);
@@ -257,36 +737,28 @@ fn add_claim(
 
 
 fn remove_claim(
-    ctx: Context<LzReceive>,
+    accounts: &LzReceive,
     wallet: Pubkey,
     topic: u64,
+    issuer: Pubkey,
 ) -> Result<()> {
-    let identity_address = find_identity_address(&ctx, wallet)?;
-
-    let instruction = Instruction {
-        program_id: ctx.accounts.identity_program.key(),
-        accounts: vec![
-            AccountMeta::new(identity_address, false),
-            AccountMeta::new(ctx.accounts.factory.owner, true),
-            AccountMeta::new(ctx.accounts.claims_account.key(), false),
-        ],
-        data: {
-            let mut data = vec![];
-            data.extend_from_slice(&topic.to_le_bytes());
-            data.extend_from_slice(&identity_address.to_bytes());
-            data
-        },
-    };
-
-
-    invoke_signed(
-        &instruction,
-        &[
-            ctx.accounts.factory.to_account_info(),
-            ctx.accounts.identity_program.to_account_info(),
-            ctx.accounts.claims_account.to_account_info(),
-        ],
-        &[],
+    find_identity_address(accounts, wallet)?;
+
+    let seeds: &[&[u8]] = &[COUNT_SEED, &[accounts.factory.id], &[accounts.factory.bump]];
+    identity_lib::cpi::remove_claim(
+        CpiContext::new_with_signer(
+            accounts.identity_program.to_account_info(),
+            identity_lib::cpi::accounts::RemoveClaim {
+                claim: accounts.claim_account.to_account_info(),
+                claim_index: accounts.claim_index_account.to_account_info(),
+                key_context: key_context(accounts),
+                claim_payer: accounts.claim_payer_account.to_account_info(),
+                index_payer: accounts.index_payer_account.to_account_info(),
+            },
+            &[seeds],
+        ),
+        topic,
+        issuer,
     )?;
 
     msg!("Claim removed from identity for wallet: {:?}", wallet);
@@ -296,38 +768,30 @@ fn remove_claim(
 }
 
 fn add_key(
-    ctx: Context<LzReceive>,
+    accounts: &LzReceive,
     wallet: Pubkey,
     key: Pubkey,
     purpose: u32,
     key_type: u32,
 ) -> Result<()> {
-    let identity_address = find_identity_address(&ctx, wallet)?;
-
-    let instruction = Instruction {
-        program_id: ctx.accounts.identity_program.key(),
-        accounts: vec![
-            AccountMeta::new(identity_address, false),
-            AccountMeta::new(ctx.accounts.factory.owner, true),
-            AccountMeta::new(ctx.accounts.keys_account.key(), false),
-        ],
-        data: {
-            let mut data = vec![];
-            data.extend_from_slice(&key.to_bytes());
-            data.extend_from_slice(&purpose.to_le_bytes());
-            data.extend_from_slice(&key_type.to_le_bytes());
-            data
-        },
-    };
-
-    invoke_signed(
-        &instruction,
-        &[
-            ctx.accounts.factory.to_account_info(),
-            ctx.accounts.identity_program.to_account_info(),
-            ctx.accounts.keys_account.to_account_info(),
-        ],
-        &[],
+    find_identity_address(accounts, wallet)?;
+
+    let seeds: &[&[u8]] = &[COUNT_SEED, &[accounts.factory.id], &[accounts.factory.bump]];
+    identity_lib::cpi::add_key(
+        CpiContext::new_with_signer(
+            accounts.identity_program.to_account_info(),
+            identity_lib::cpi::accounts::AddKey {
+                key_context: key_context(accounts),
+                key_account: accounts.key_account.to_account_info(),
+                purpose_keys: accounts.purpose_keys_account.to_account_info(),
+                payer: accounts.payer.to_account_info(),
+                system_program: accounts.system_program.to_account_info(),
+            },
+            &[seeds],
+        ),
+        key,
+        purpose,
+        key_type,
     )?;
 
     msg!("Key added to identity for wallet: {:?}", wallet);
@@ -337,36 +801,27 @@ fn add_key(
 }
 
 fn remove_key(
-    ctx: Context<LzReceive>,
+    accounts: &LzReceive,
     wallet: Pubkey,
     key: Pubkey,
     purpose: u32,
 ) -> Result<()> {
-    let identity_address = find_identity_address(&ctx, wallet)?;
-
-    let instruction = Instruction {
-        program_id: ctx.accounts.identity_program.key(),
-        accounts: vec![
-            AccountMeta::new(identity_address, false),
-            AccountMeta::new(ctx.accounts.factory.owner, true),
-            AccountMeta::new(ctx.accounts.keys_account.key(), false),
-        ],
-        data: {
-            let mut data = vec![];
-            data.extend_from_slice(&key.to_bytes());
-            data.extend_from_slice(&purpose.to_le_bytes());
-            data
-        },
-    };
-
-    invoke_signed(
-        &instruction,
-        &[
-            ctx.accounts.factory.to_account_info(),
-            ctx.accounts.identity_program.to_account_info(),
-            ctx.accounts.keys_account.to_account_info(),
-        ],
-        &[],
+    find_identity_address(accounts, wallet)?;
+
+    let seeds: &[&[u8]] = &[COUNT_SEED, &[accounts.factory.id], &[accounts.factory.bump]];
+    identity_lib::cpi::remove_key(
+        CpiContext::new_with_signer(
+            accounts.identity_program.to_account_info(),
+            identity_lib::cpi::accounts::RemoveKey {
+                key_context: key_context(accounts),
+                key_account: accounts.key_account.to_account_info(),
+                purpose_keys: accounts.purpose_keys_account.to_account_info(),
+                payer: accounts.payer.to_account_info(),
+            },
+            &[seeds],
+        ),
+        key,
+        purpose,
     )?;
 
     msg!("Key removed from identity for wallet: {:?}", wallet);
@@ -375,13 +830,42 @@ fn remove_key(
     Ok(())
 }
 
-fn find_identity_address(ctx: &Context<LzReceive>, wallet: Pubkey) -> Result<Pubkey> {
-    ctx.accounts.factory
-        .wallet_to_identity
-        .iter()
-        .find(|&&(w, _)| w == wallet)
-        .map(|&(_, identity)| identity)
-        .ok_or(ErrorCode::WalletNotLinked.into())
+/// Accounts for the identity program's `_Key` context, with the factory as signer.
+fn key_context<'info>(accounts: &LzReceive<'info>) -> identity_lib::cpi::accounts::_Key<'info> {
+    identity_lib::cpi::accounts::_Key {
+        identity_account: accounts.identity_account.to_account_info(),
+        signer_key: None,
+        signer: accounts.factory.to_account_info(),
+    }
+}
+
+/// Claims bridged by the factory are signed by EVM claim issuers, whose
+/// signatures are checked against the Claim keys the factory recorded for
+/// them with `set_evm_issuer`.
+fn claim_context<'info>(accounts: &LzReceive<'info>) -> identity_lib::cpi::accounts::ClaimContext<'info> {
+    identity_lib::cpi::accounts::ClaimContext {
+        claim: accounts.claim_account.to_account_info(),
+        claim_index: accounts.claim_index_account.to_account_info(),
+        key_context: key_context(accounts),
+        instructions: accounts.instructions.to_account_info(),
+        claim_issuer_program: None,
+        issuer_keys: None,
+        issuer_revoked_claim: None,
+        claim_key: None,
+        evm_issuer: accounts.evm_issuer_account.as_ref().map(|a| a.to_account_info()),
+        payer: accounts.payer.to_account_info(),
+        system_program: accounts.system_program.to_account_info(),
+    }
+}
+
+/// Looks up the identity linked to `wallet` and checks it is the identity
+/// account supplied to the instruction.
+fn find_identity_address(accounts: &LzReceive, wallet: Pubkey) -> Result<Pubkey> {
+    let identity = linked_identity(&accounts.wallet_account, &accounts.factory.key(), &wallet)?;
+
+    require_keys_eq!(accounts.identity_account.key(), identity, ErrorCode::InvalidIdentity);
+
+    Ok(identity)
 }
 
 fn decode_add_key_payload(payload: &[u8]) -> anchor_lang::Result<(Pubkey, Pubkey, u32, u32)> {
@@ -439,9 +923,46 @@ fn decode_create_identity_payload(payload: &[u8]) -> std::result::Result<(Pubkey
     Ok((identity_owner, salt_bytes))
 }
 
-fn decode_add_claim_payload(
+/// `wallet | salt | key count (u8) | keys`, each key being its `KeyType` (u32)
+/// followed by an Ed25519 key or an Ethereum address.
+fn decode_create_identity_with_management_keys_payload(
     payload: &[u8],
-) -> std::result::Result<(Pubkey, u64, u64, Pubkey, [u8; 64], Vec<u8>, String), ProgramError> {
+) -> anchor_lang::Result<(Pubkey, [u8; 32], Vec<ManagementKey>)> {
+    let mut cursor = std::io::Cursor::new(payload);
+
+    let mut wallet_bytes = [0u8; 32];
+    cursor.read_exact(&mut wallet_bytes)?;
+    let wallet = Pubkey::new_from_array(wallet_bytes);
+
+    let mut salt = [0u8; 32];
+    cursor.read_exact(&mut salt)?;
+
+    let key_count = cursor.read_u8()?;
+    let mut management_keys = Vec::with_capacity(key_count as usize);
+    for _ in 0..key_count {
+        let management_key = match cursor.read_u32::<LittleEndian>()? {
+            key_type if key_type == KeyType::Ed25519 as u32 => {
+                let mut key_bytes = [0u8; 32];
+                cursor.read_exact(&mut key_bytes)?;
+                ManagementKey::Ed25519(Pubkey::new_from_array(key_bytes))
+            }
+            key_type if key_type == KeyType::ECDSA as u32 => {
+                let mut address = [0u8; 20];
+                cursor.read_exact(&mut address)?;
+                ManagementKey::Ethereum(address)
+            }
+            _ => return Err(ErrorCode::InvalidKeyType.into()),
+        };
+        management_keys.push(management_key);
+    }
+
+    Ok((wallet, salt, management_keys))
+}
+
+/// `(wallet, topic, scheme, issuer, issuer_wallet, signature, data, uri)` of an add-claim message.
+type AddClaimPayload = (Pubkey, u64, u64, Pubkey, Pubkey, Vec<u8>, Vec<u8>, String);
+
+fn decode_add_claim_payload(payload: &[u8]) -> std::result::Result<AddClaimPayload, ProgramError> {
     let mut cursor = std::io::Cursor::new(payload);
 
     // Decode `wallet`
@@ -453,11 +974,20 @@ fn decode_add_claim_payload(
     let topic = cursor.read_u64::<LittleEndian>()?;
     let scheme = cursor.read_u64::<LittleEndian>()?;
 
-    // Issuer Wallet and Issuer use `wallet`
-    let issuer_wallet = wallet;
+    // Decode the signing key, an Ethereum address for ECDSA claims (see `eth_address_pubkey`)
+    let mut issuer_wallet_bytes = [0u8; 32];
+    cursor.read_exact(&mut issuer_wallet_bytes)?;
+    let issuer_wallet = Pubkey::new_from_array(issuer_wallet_bytes);
 
-    // Decode the remaining fields; signature, data, and uri
-    let mut signature = [0u8; 64];
+    // Decode the issuer identity
+    let mut issuer_bytes = [0u8; 32];
+    cursor.read_exact(&mut issuer_bytes)?;
+    let issuer = Pubkey::new_from_array(issuer_bytes);
+
+    // Decode the remaining fields; signature, data, and uri.
+    // Signatures are length-prefixed: 64 bytes for Ed25519, 65 for ECDSA.
+    let signature_size = cursor.read_u32::<LittleEndian>()? as usize;
+    let mut signature = vec![0u8; signature_size];
     cursor.read_exact(&mut signature)?;
 
     let data_size = cursor.read_u32::<LittleEndian>()? as usize;
@@ -468,12 +998,12 @@ fn decode_add_claim_payload(
     cursor.read_to_end(&mut uri_buffer)?;
     let uri = String::from_utf8(uri_buffer).map_err(|_| ProgramError::InvalidInstructionData)?;
 
-    Ok((wallet, topic, scheme, issuer_wallet, signature, data, uri))
+    Ok((wallet, topic, scheme, issuer_wallet, issuer, signature, data, uri))
 }
 
 fn decode_remove_claim_payload(
     payload: &[u8],
-) -> anchor_lang::Result<(Pubkey, u64)> {
+) -> anchor_lang::Result<(Pubkey, u64, Pubkey)> {
     let mut cursor = std::io::Cursor::new(payload);
 
     let mut wallet_bytes = [0u8; 32];
@@ -484,105 +1014,184 @@ fn decode_remove_claim_payload(
 
     let mut issuer_bytes = [0u8; 32];
     cursor.read_exact(&mut issuer_bytes)?;
+    let issuer = Pubkey::new_from_array(issuer_bytes);
+
+    Ok((wallet, topic, issuer))
+}
 
-    Ok((wallet, topic))
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(init, payer = payer, space = Factory::LEN)]
+    pub factory: Account<'info, Factory>,
+    #[account(mut, signer)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct AddKey<'info> {
+pub struct CreateIdentity<'info> {
     #[account(mut)]
     pub factory: Account<'info, Factory>,
     #[account(mut)]
-    pub keys_account: Account<'info, KeysAccount>,
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(address = identity_lib::ID)]
     pub identity_program: Program<'info, Identity>,
+    /// CHECK: Initialized by the identity program at its `[IDENTITY_SEED, factory, wallet, salt]` PDA.
+    #[account(mut)]
+    pub identity_account: AccountInfo<'info>,
+    /// CHECK: Initialized by the identity program at its `[KEY_SEED, identity, key_hash]` PDA.
+    #[account(mut)]
+    pub management_key_account: AccountInfo<'info>,
+    /// CHECK: Initialized by the identity program at its `[KEYS_SEED, identity, purpose]` PDA.
+    #[account(mut)]
+    pub management_keys_account: AccountInfo<'info>,
+    /// CHECK: Created at the `[SALT_SEED, factory, salt]` PDA, see `take_salt`.
+    #[account(mut)]
+    pub salt_account: AccountInfo<'info>,
+    /// CHECK: Created at the `[WALLET_SEED, factory, wallet]` PDA, see `add_wallet`.
+    #[account(mut)]
+    pub wallet_account: AccountInfo<'info>,
+    /// CHECK: The `[IDENTITY_WALLETS_SEED, factory, identity]` PDA, see `add_wallet`.
+    #[account(mut)]
+    pub identity_wallets_account: AccountInfo<'info>,
 }
 
-#[account]
-pub struct KeysAccount {
-    pub keys: Vec<NewKey>,
-}
-
-#[derive(Clone, Copy, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
-pub enum KeyPurpose {
-    Management = 1,
-    Action = 2,
-    Claim = 3,
-    Encryption = 4,
-}
-
-impl TryFrom<u32> for KeyPurpose {
-    type Error = Error; 
-
-    fn try_from(value: u32) -> std::result::Result<KeyPurpose, Error> {
-        match value {
-            1 => Ok(KeyPurpose::Management),
-            2 => Ok(KeyPurpose::Action),
-            3 => Ok(KeyPurpose::Claim),
-            4 => Ok(KeyPurpose::Encryption),
-            _ => Err(ErrorCode::InvalidKeyPurpose.into()),
-        }
-    }
-}
-
-#[derive(Clone, Copy, AnchorSerialize, AnchorDeserialize)]
-pub enum KeyType {
-    ECDSA = 1,
-    RSA = 2,
-}
-
-#[account]
-pub struct NewKey {
-    pub purposes: Vec<KeyPurpose>,
-    pub key_type: KeyType,
-    pub key: [u8; 32],
-}
-
-impl TryFrom<u32> for KeyType {
-    type Error = Error;
-
-    fn try_from(value: u32) -> std::result::Result<KeyType, Error> {
-        match value {
-            1 => Ok(KeyType::ECDSA),
-            2 => Ok(KeyType::RSA),
-            _ => Err(ErrorCode::InvalidKeyType.into()),
-        }
-    }
+#[derive(Accounts)]
+pub struct CreateIdentityWithManagementKeys<'info> {
+    #[account(mut)]
+    pub factory: Account<'info, Factory>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(address = identity_lib::ID)]
+    pub identity_program: Program<'info, Identity>,
+    /// CHECK: Initialized by the identity program at its `[IDENTITY_SEED, factory, wallet, salt]` PDA.
+    #[account(mut)]
+    pub identity_account: AccountInfo<'info>,
+    /// CHECK: Initialized by the identity program at its `[KEYS_SEED, identity, purpose]` PDA.
+    #[account(mut)]
+    pub management_keys_account: AccountInfo<'info>,
+    /// CHECK: Created at the `[SALT_SEED, factory, salt]` PDA, see `take_salt`.
+    #[account(mut)]
+    pub salt_account: AccountInfo<'info>,
+    /// CHECK: Created at the `[WALLET_SEED, factory, wallet]` PDA, see `add_wallet`.
+    #[account(mut)]
+    pub wallet_account: AccountInfo<'info>,
+    /// CHECK: The `[IDENTITY_WALLETS_SEED, factory, identity]` PDA, see `add_wallet`.
+    #[account(mut)]
+    pub identity_wallets_account: AccountInfo<'info>,
 }
 
-#[account]
-pub struct ClaimsAccount {
-    pub claims: Vec<([u8; 32], Claim)>, 
+#[derive(Accounts)]
+#[instruction(token_factory: Pubkey)]
+pub struct AddTokenFactory<'info> {
+    pub factory: Account<'info, Factory>,
+    #[account(
+        init,
+        payer = payer,
+        space = TokenFactory::LEN,
+        seeds = [TOKEN_FACTORY_SEED, factory.key().as_ref(), token_factory.as_ref()],
+        bump
+    )]
+    pub token_factory_account: Account<'info, TokenFactory>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
-#[account]
-pub struct Claim {
-    topic: u64,          
-    scheme: u64,
-    issuer_wallet: Pubkey,
-    issuer: Pubkey,
-    signature: [u8; 64], 
-    data: Vec<u8>,      
-    uri: String,
+#[derive(Accounts)]
+#[instruction(token_factory: Pubkey)]
+pub struct RemoveTokenFactory<'info> {
+    pub factory: Account<'info, Factory>,
+    #[account(
+        mut,
+        close = payer,
+        seeds = [TOKEN_FACTORY_SEED, factory.key().as_ref(), token_factory.as_ref()],
+        bump = token_factory_account.bump
+    )]
+    pub token_factory_account: Account<'info, TokenFactory>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(init, payer = payer, space = Factory::calc_size(100, 100))]
+pub struct SetEvmIssuer<'info> {
+    #[account(seeds = [COUNT_SEED, &[factory.id]], bump = factory.bump)]
     pub factory: Account<'info, Factory>,
-    #[account(mut, signer)]
+    /// CHECK: Initialized by the identity program at its `[EVM_ISSUER_SEED, factory, issuer]` PDA.
+    #[account(mut)]
+    pub evm_issuer: AccountInfo<'info>,
+    #[account(address = identity_lib::ID)]
+    pub identity_program: Program<'info, Identity>,
+    #[account(mut)]
     pub payer: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct CreateIdentity<'info> {
+#[instruction(token: Pubkey)]
+pub struct CreateTokenIdentity<'info> {
     #[account(mut)]
     pub factory: Account<'info, Factory>,
+    /// Registration of the signing token factory.
+    #[account(
+        seeds = [TOKEN_FACTORY_SEED, factory.key().as_ref(), token_factory.key().as_ref()],
+        bump = token_factory_account.bump
+    )]
+    pub token_factory_account: Account<'info, TokenFactory>,
+    pub token_factory: Signer<'info>,
+    /// The token, an SPL Token or Token-2022 mint.
+    #[account(address = token @ ErrorCode::InvalidToken)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        space = TokenIdentity::LEN,
+        seeds = [TOKEN_SEED, factory.key().as_ref(), token.as_ref()],
+        bump
+    )]
+    pub token_identity: Account<'info, TokenIdentity>,
     #[account(mut)]
     pub payer: Signer<'info>,
     pub system_program: Program<'info, System>,
     #[account(address = identity_lib::ID)]
     pub identity_program: Program<'info, Identity>,
+    /// CHECK: Created at the `[SALT_SEED, factory, salt]` PDA, see `take_salt`.
+    #[account(mut)]
+    pub salt_account: AccountInfo<'info>,
+    /// CHECK: Initialized by the identity program at its `[IDENTITY_SEED, factory, token, salt]` PDA.
+    #[account(mut)]
+    pub identity_account: AccountInfo<'info>,
+    /// CHECK: Initialized by the identity program at its `[KEY_SEED, identity, key_hash]` PDA.
+    #[account(mut)]
+    pub management_key_account: AccountInfo<'info>,
+    /// CHECK: Initialized by the identity program at its `[KEYS_SEED, identity, purpose]` PDA.
+    #[account(mut)]
+    pub management_keys_account: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetToken<'info> {
+    pub factory: Account<'info, Factory>,
+    #[account(constraint = identity_account.factory == factory.key() @ ErrorCode::TokenNotLinked)]
+    pub identity_account: Account<'info, IdentityAccount>,
+    /// A token identity's PDA is derived from its token, which it holds as its wallet.
+    #[account(
+        seeds = [TOKEN_SEED, factory.key().as_ref(), identity_account.wallet.as_ref()],
+        bump = token_identity.bump,
+        constraint = token_identity.identity == identity_account.key() @ ErrorCode::TokenNotLinked
+    )]
+    pub token_identity: Account<'info, TokenIdentity>,
+}
+
+#[derive(Accounts)]
+#[instruction(salt: [u8; 32])]
+pub struct IsSaltTaken<'info> {
+    pub factory: Account<'info, Factory>,
+    /// CHECK: Only exists, owned by the factory program, once the salt is taken.
+    #[account(seeds = [SALT_SEED, factory.key().as_ref(), salt.as_ref()], bump)]
+    pub salt_account: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
@@ -594,8 +1203,81 @@ pub struct LinkedWallets<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(wallet: Pubkey, identity: Pubkey)]
+pub struct LinkWallet<'info> {
+    #[account(seeds = [COUNT_SEED, &[factory.id]], bump = factory.bump)]
+    pub factory: Account<'info, Factory>,
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        address = identity @ ErrorCode::InvalidIdentity,
+        constraint = identity_account.factory == factory.key() @ ErrorCode::InvalidIdentity
+    )]
+    pub identity_account: Account<'info, IdentityAccount>,
+    /// The signer's own link, when it is a wallet of the identity.
+    #[account(seeds = [WALLET_SEED, factory.key().as_ref(), signer.key().as_ref()], bump = signer_wallet_account.bump)]
+    pub signer_wallet_account: Option<Account<'info, WalletIdentity>>,
+    /// The signer's key, when it is a management key of the identity.
+    #[account(
+        seeds = [KEY_SEED, identity.as_ref(), &hash_key(&signer.key())],
+        bump = signer_key.bump,
+        seeds::program = identity_lib::ID
+    )]
+    pub signer_key: Option<Account<'info, KeyAccount>>,
+    /// CHECK: Created at the `[WALLET_SEED, factory, wallet]` PDA, see `add_wallet`.
+    #[account(mut)]
+    pub wallet_account: AccountInfo<'info>,
+    /// CHECK: The `[IDENTITY_WALLETS_SEED, factory, identity]` PDA, see `add_wallet`.
+    #[account(mut)]
+    pub identity_wallets_account: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct UnlinkWallet<'info> {
+    #[account(seeds = [COUNT_SEED, &[factory.id]], bump = factory.bump)]
+    pub factory: Account<'info, Factory>,
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        close = signer,
+        seeds = [WALLET_SEED, factory.key().as_ref(), wallet.as_ref()],
+        bump = wallet_account.bump
+    )]
+    pub wallet_account: Account<'info, WalletIdentity>,
+    /// Only the factory that deployed the identity unlinks its wallets.
+    #[account(
+        address = wallet_account.identity @ ErrorCode::InvalidIdentity,
+        constraint = identity_account.factory == factory.key() @ ErrorCode::InvalidIdentity
+    )]
+    pub identity_account: Account<'info, IdentityAccount>,
+    #[account(
+        mut,
+        seeds = [IDENTITY_WALLETS_SEED, factory.key().as_ref(), wallet_account.identity.as_ref()],
+        bump = identity_wallets_account.bump
+    )]
+    pub identity_wallets_account: Account<'info, IdentityWallets>,
+}
+
+#[derive(Accounts)]
+#[instruction(identity: Pubkey)]
+pub struct GetWallets<'info> {
+    pub factory: Account<'info, Factory>,
+    #[account(
+        seeds = [IDENTITY_WALLETS_SEED, factory.key().as_ref(), identity.as_ref()],
+        bump = identity_wallets_account.bump
+    )]
+    pub identity_wallets_account: Account<'info, IdentityWallets>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
 pub struct GetIdentity<'info> {
     pub factory: Account<'info, Factory>,
+    #[account(seeds = [WALLET_SEED, factory.key().as_ref(), wallet.as_ref()], bump = wallet_account.bump)]
+    pub wallet_account: Account<'info, WalletIdentity>,
 }
 
 #[account]
@@ -603,9 +1285,6 @@ pub struct GetIdentity<'info> {
 pub struct Factory {
     pub initialized: bool,
     pub owner: Pubkey,
-    pub identity_addresses: Vec<Pubkey>,
-    pub linked_wallets: Vec<Pubkey>,
-    pub wallet_to_identity: Vec<(Pubkey, Pubkey)>,
     pub id: u8,
     pub bump: u8,
     pub endpoint_program: Pubkey,
@@ -613,20 +1292,78 @@ pub struct Factory {
 
 
 impl Factory {
-    pub fn calc_size(id_count: usize, wallet_count: usize) -> usize {
-        // Calculate the size of the Factory account
-        // 8 bytes for discriminator
-        // 1 byte for initialized
-        // 32 bytes for owner
-        // 4 bytes for the length of identity_addresses vector
-        // id_count * 32 bytes for each Pubkey in identity_addresses
-        // 4 bytes for the length of linked_wallets vector
-        // wallet_count * 32 bytes for each Pubkey in linked_wallets
-        // 1 byte for id
-        // 1 byte for bump
-        // 32 bytes for endpoint_program
-        8 + 1 + 32 + 4 + id_count * 32 + 4 + wallet_count * 32 + 1 + 1 + 32
-    }
+    pub const LEN: usize = 8 + 1 + 32 + 1 + 1 + 32;
+}
+
+/// Links a wallet to its identity, stored at `[WALLET_SEED, factory, wallet]`.
+#[account]
+pub struct WalletIdentity {
+    pub bump: u8,
+    pub wallet: Pubkey,
+    pub identity: Pubkey,
+}
+
+impl WalletIdentity {
+    const LEN: usize = 8 + 1 + 32 + 32;
+}
+
+/// Wallets linked to an identity, stored at `[IDENTITY_WALLETS_SEED, factory, identity]`.
+#[account]
+pub struct IdentityWallets {
+    pub bump: u8,
+    pub identity: Pubkey,
+    pub wallets: Vec<Pubkey>,
+}
+
+impl IdentityWallets {
+    /// Same limit as the EVM `IdFactory`.
+    pub const MAX_WALLETS: usize = 101;
+    const LEN: usize = 8 + 1 + 32 + 4 + Self::MAX_WALLETS * 32;
+}
+
+/// Marks a salt as used by `identity`.
+#[account]
+pub struct Salt {
+    pub bump: u8,
+    pub identity: Pubkey,
+}
+
+impl Salt {
+    const LEN: usize = 8 + 1 + 32;
+}
+
+/// Marks `token_factory` as allowed to create token identities.
+#[account]
+pub struct TokenFactory {
+    pub bump: u8,
+    pub token_factory: Pubkey,
+}
+
+impl TokenFactory {
+    const LEN: usize = 8 + 1 + 32;
+}
+
+/// Link between a token and its identity, at `[TOKEN_SEED, factory, token]`.
+#[account]
+pub struct TokenIdentity {
+    pub bump: u8,
+    pub token: Pubkey,
+    pub identity: Pubkey,
+}
+
+impl TokenIdentity {
+    const LEN: usize = 8 + 1 + 32 + 32;
+}
+
+#[event]
+pub struct PeerSet {
+    pub eid: u32,
+    pub peer: [u8; 32],
+}
+
+#[event]
+pub struct EnforcedOptionsSet {
+    pub eid: u32,
 }
 
 #[event]
@@ -638,6 +1375,23 @@ pub struct FactoryInitialized {
 pub struct IdentityCreated {
     pub wallet: Pubkey,
     pub identity_address: Pubkey,
+    pub salt: [u8; 32],
+}
+
+#[event]
+pub struct TokenLinked {
+    pub token: Pubkey,
+    pub identity: Pubkey,
+}
+
+#[event]
+pub struct TokenFactoryAdded {
+    pub token_factory: Pubkey,
+}
+
+#[event]
+pub struct TokenFactoryRemoved {
+    pub token_factory: Pubkey,
 }
 
 #[event]
@@ -700,4 +1454,32 @@ pub enum ErrorCode {
     InvalidKeyPurpose,
     #[msg("The specified key type is invalid.")]
     InvalidKeyType,
+    #[msg("The identity account does not match the wallet's identity.")]
+    InvalidIdentity,
+    #[msg("The identity is not linked to a token.")]
+    TokenNotLinked,
+    #[msg("The salt has already been used to create an identity.")]
+    SaltTaken,
+    #[msg("The salt account is not the salt's PDA.")]
+    InvalidSaltAccount,
+    #[msg("The message was not sent by the peer configured for its source endpoint.")]
+    UnknownPeer,
+    #[msg("The wallet is already linked to an identity.")]
+    WalletAlreadyLinked,
+    #[msg("The wallet account is not the wallet's PDA.")]
+    InvalidWalletAccount,
+    #[msg("The wallet is not linked to this identity.")]
+    WalletNotLinkedToIdentity,
+    #[msg("The identity's last wallet cannot be unlinked.")]
+    LastWallet,
+    #[msg("The identity has reached its maximum number of wallets.")]
+    TooManyWallets,
+    #[msg("The method is not an outbound message.")]
+    UnknownMethod,
+    #[msg("The options exceed the maximum length.")]
+    OptionsTooLong,
+    #[msg("The token is not the given mint.")]
+    InvalidToken,
+    #[msg("The identity needs more than one management key's approval.")]
+    ManagementThresholdNotMet,
 }
\ No newline at end of file
@@ -1,94 +1,152 @@
-use anchor_lang::{prelude::*, solana_program::hash::hash, Key as AnchorKey,};
-use crate::utils::verify_ed25519_ix;
+// The `cpi` module generated by `#[program]` mirrors the arguments of the claim instructions
+#![allow(clippy::too_many_arguments)]
+
+use anchor_lang::{prelude::*, solana_program::{hash::hash, keccak}, system_program, Key as AnchorKey,};
+use crate::utils::{eth_signed_message, recover_eth_address, verify_ed25519_in_tx, verify_secp256k1_ix};
 use solana_program::sysvar::instructions::{load_instruction_at_checked, ID as IX_ID};
-use solana_program::ed25519_program::ID as ED25519_ID;
-use solana_program::instruction::Instruction;
-// use claim_issuer_lib::claim_issuer;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program::invoke_signed;
+use claim_issuer_lib::{claim_message, program::ClaimIssuer};
 pub mod utils;
 pub mod error;
 
 declare_id!("Gono8cHfm7zUfgdmSEcuvCx2WsN2LEK2ymtdoS67cVDK");
 
+pub const IDENTITY_SEED: &[u8] = b"identity";
+pub const KEYS_SEED: &[u8] = b"keys";
+pub const KEY_SEED: &[u8] = b"key";
+pub const CLAIMS_SEED: &[u8] = b"claims";
+pub const CLAIM_SEED: &[u8] = b"claim";
+pub const EXECUTION_SEED: &[u8] = b"execution";
+pub const EVM_ISSUER_SEED: &[u8] = b"evm_issuer";
+
 #[program]
 pub mod identity {
     pub use super::*;
 
-    pub fn get_initialized(_ctx: Context<Initialize>) -> Result<bool> {
+    pub fn get_initialized(_ctx: Context<GetIdentity>) -> Result<bool> {
         Ok(_ctx.accounts.identity_account.initialized)
     }
 
-    pub fn initialize(_ctx: Context<Initialize>, initial_management_key: Pubkey, factory: Pubkey) -> Result<()> {
+    /// Creates the identity at `[IDENTITY_SEED, factory, wallet, salt]`.
+    ///
+    /// The factory must sign, which it does through its PDA when calling in
+    /// via CPI, so identity addresses can be predicted from `(wallet, salt)`.
+    pub fn initialize(_ctx: Context<Initialize>, wallet: Pubkey, salt: [u8; 32], initial_management_key: Pubkey) -> Result<()> {
         let identity_account = &mut _ctx.accounts.identity_account;
-        let keys = &mut _ctx.accounts.keys;
-        let key_account = &mut _ctx.accounts.key_account;
 
         if identity_account.initialized {
             return Err(Error::AlreadyInitialized.into());
         }
 
         let key_hash = hash_key(&initial_management_key);
-        
-        key_account.purposes = vec![KeyPurpose::Management];
-        key_account.key_type = KeyType::ECDSA;
-        key_account.key = key_hash;
 
-        let new_key = NewKey {
-            purposes: key_account.purposes.clone(),
-            key_type: key_account.key_type,
-            key: key_account.key,
-        };
+        _ctx.accounts.management_key.set_inner(KeyAccount {
+            bump: _ctx.bumps.management_key,
+            identity: identity_account.key(),
+            key: key_hash,
+            purposes: KeyPurpose::Management.mask(),
+            key_type: KeyType::Ed25519,
+        });
+        _ctx.accounts.management_keys.set_inner(PurposeKeysAccount {
+            bump: _ctx.bumps.management_keys,
+            purpose: KeyPurpose::Management as u32,
+            keys: vec![key_hash],
+        });
+
+        setup_identity(identity_account, _ctx.accounts.factory.key(), true, wallet, salt, _ctx.bumps.identity_account);
+
+        Ok(())
+    }
+
+    /// `initialize` with several management keys, Ed25519 or Ethereum-address,
+    /// whose `[KEY_SEED, identity, key_hash]` PDAs are the remaining accounts, in order.
+    ///
+    /// The identity is managed by these keys alone: unlike with `initialize`,
+    /// the factory gets no management rights over it.
+    pub fn initialize_with_management_keys<'info>(
+        _ctx: Context<'_, '_, 'info, 'info, InitializeWithManagementKeys<'info>>,
+        wallet: Pubkey,
+        salt: [u8; 32],
+        management_keys: Vec<ManagementKey>,
+    ) -> Result<()> {
+        if management_keys.is_empty() {
+            return Err(Error::NoManagementKeys.into());
+        }
+        if management_keys.len() > PurposeKeysAccount::MAX_KEYS {
+            return Err(Error::TooManyKeys.into());
+        }
+        if _ctx.remaining_accounts.len() != management_keys.len() {
+            return Err(Error::InvalidKeyAccount.into());
+        }
+
+        let identity = _ctx.accounts.identity_account.key();
+        let user = _ctx.accounts.user.to_account_info();
+        let system_program = _ctx.accounts.system_program.to_account_info();
+
+        let mut key_hashes = Vec::with_capacity(management_keys.len());
+        for (management_key, key_info) in management_keys.iter().zip(_ctx.remaining_accounts) {
+            let key_hash = management_key.hash();
+            if key_hashes.contains(&key_hash) {
+                return Err(Error::KeyConflict.into());
+            }
+
+            create_key_account(key_info, &user, &system_program, KeyAccount {
+                bump: 0,
+                identity,
+                key: key_hash,
+                purposes: KeyPurpose::Management.mask(),
+                key_type: management_key.key_type(),
+            })?;
+            key_hashes.push(key_hash);
+        }
+
+        _ctx.accounts.management_keys.set_inner(PurposeKeysAccount {
+            bump: _ctx.bumps.management_keys,
+            purpose: KeyPurpose::Management as u32,
+            keys: key_hashes,
+        });
 
-        keys.keys.push(new_key);
-        identity_account.initialized = true;
-        identity_account.factory = factory;
+        let identity_account = &mut _ctx.accounts.identity_account;
+        if identity_account.initialized {
+            return Err(Error::AlreadyInitialized.into());
+        }
+        setup_identity(identity_account, _ctx.accounts.factory.key(), false, wallet, salt, _ctx.bumps.identity_account);
 
         Ok(())
     }
 
-    pub fn get_key(_ctx: Context< _Key>, key: Pubkey) -> Result<NewKey> {        
-        let key_hash = hash_key(&key);
-        let keys_account = &_ctx.accounts.keys;
-        keys_account.keys.iter()
-            .find(|k| k.key == key_hash)
-            .cloned()
-            .ok_or(Error::KeyNotFound.into())
+    pub fn get_key(_ctx: Context<GetKey>, _key: Pubkey) -> Result<KeyAccount> {
+        Ok(_ctx.accounts.key_account.clone().into_inner())
     }
 
-    pub fn get_keys(_ctx: Context<_Key>) -> Result<Vec<NewKey>> {
-        Ok(_ctx.accounts.keys.keys.clone())   
+    /// ERC-734 `getKeysByPurpose`: hashes of the keys holding `purpose`.
+    pub fn keys_by_purpose(_ctx: Context<KeysByPurpose>, _purpose: u32) -> Result<Vec<[u8; 32]>> {
+        Ok(_ctx.accounts.purpose_keys.keys.clone())
     }
 
-    pub fn add_key(_ctx: Context<_Key>, manager: Pubkey, key: Pubkey, purpose: u32, key_type: u32 ) -> Result<()> {
-        // identity_require_auth(&_ctx.accounts.keys, &manager, KeyPurpose::Management)?;
+    /// Adds `purpose` to the key at `[KEY_SEED, identity, key_hash]`, creating it
+    /// if needed, and lists the key in the index of the purpose.
+    pub fn add_key(_ctx: Context<AddKey>, key: Pubkey, purpose: u32, key_type: u32 ) -> Result<()> {
+        let key_context = &_ctx.accounts.key_context;
+        identity_require_auth(&key_context.identity_account, key_context.signer_key.as_ref(), &key_context.signer, KeyPurpose::Management)?;
+        let manager = key_context.signer.key();
         
         let key_hash = hash_key( &key);
         let key_purpose = KeyPurpose::try_from(purpose).map_err(|_| anchor_lang::error::Error::from(Error::InvalidKeyPurpose))?;
         let key_type_enum = KeyType::try_from(key_type).map_err(|_| anchor_lang::error::Error::from(Error::InvalidKeyType))?;
 
-        let keys = &mut _ctx.accounts.keys;
-        let mut key_found = false;
-        
-        for i in 0..keys.keys.len() {
-            let k = &mut keys.keys[i];
-            if k.key == key_hash {
-                if k.purposes.contains(&key_purpose) {
-                    return Err(Error::KeyConflict.into());
-                } else {
-                    k.purposes.push(key_purpose);
-                    key_found = true;
-                    break;
-                }
-            }
-        }
-
-        if !key_found {
-            let new_key = NewKey {
-                purposes: vec![key_purpose],
-                key_type: key_type_enum,
-                key: key_hash.clone(),
-            };
-            keys.keys.push(new_key);
-        }
+        let accounts = &mut *_ctx.accounts;
+        accounts.key_account.bump = _ctx.bumps.key_account;
+        accounts.purpose_keys.bump = _ctx.bumps.purpose_keys;
+        insert_key(
+            accounts.key_context.identity_account.key(),
+            &mut accounts.key_account,
+            &mut accounts.purpose_keys,
+            key_hash,
+            key_purpose,
+            key_type_enum,
+        )?;
 
         emit!(KeyAdded {
             manager,
@@ -100,32 +158,23 @@ pub mod identity {
         Ok(())
     }
 
-    pub fn remove_key<'info>(_ctx: Context< _Key>, manager: Pubkey, key: Pubkey, purpose: u32) -> Result<()> {
-        // identity_require_auth(&_ctx.accounts.keys, &manager, KeyPurpose::Management)?;
+    /// Removes `purpose` from the key, closing its account once it has no
+    /// purpose left and refunding the rent to `payer`.
+    pub fn remove_key(_ctx: Context<RemoveKey>, key: Pubkey, purpose: u32) -> Result<()> {
+        let key_context = &_ctx.accounts.key_context;
+        identity_require_auth(&key_context.identity_account, key_context.signer_key.as_ref(), &key_context.signer, KeyPurpose::Management)?;
+        let manager = key_context.signer.key();
 
-        let key_hash = hash_key( &key);
         let key_purpose = KeyPurpose::try_from(purpose).map_err(|_| Error::InvalidKeyPurpose)?;
     
-        let keys_account = &mut _ctx.accounts.keys;
-    
-        if !keys_account.keys.iter().any(|k| k.key == key_hash) {
-            return Err(Error::KeyNotFound.into());
-        }
-    
-        for i in 0..keys_account.keys.len() {
-            if keys_account.keys[i].key == key_hash {
-                if let Some(pos) = keys_account.keys[i].purposes.iter().position(|&p| p == key_purpose) {
-                    keys_account.keys[i].purposes.remove(pos);
-                    
-                    if keys_account.keys[i].purposes.is_empty() {
-                        keys_account.keys.remove(i);
-                    }
-                } else {
-                    return Err(Error::KeyDoesNotHavePurpose.into());
-                }
-                break;
-            }
-        }
+        let accounts = &mut *_ctx.accounts;
+        delete_key(
+            &mut accounts.key_context.identity_account,
+            &mut accounts.key_account,
+            &mut accounts.purpose_keys,
+            key_purpose,
+            &accounts.payer,
+        )?;
 
         emit!(KeyRemoved {
             manager,
@@ -135,29 +184,138 @@ pub mod identity {
         Ok(())
     }
 
+    /// Adds an Ethereum address as an ECDSA key, hashed as `keccak256(abi.encode(address))`
+    /// like ERC-734 keys on EVM chains.
+    pub fn add_eth_key(_ctx: Context<AddEthKey>, key: [u8; 20], purpose: u32) -> Result<()> {
+        let key_context = &_ctx.accounts.key_context;
+        identity_require_auth(&key_context.identity_account, key_context.signer_key.as_ref(), &key_context.signer, KeyPurpose::Management)?;
+        let manager = key_context.signer.key();
+
+        let key_purpose = KeyPurpose::try_from(purpose)?;
+        let accounts = &mut *_ctx.accounts;
+        accounts.key_account.bump = _ctx.bumps.key_account;
+        accounts.purpose_keys.bump = _ctx.bumps.purpose_keys;
+        insert_key(
+            accounts.key_context.identity_account.key(),
+            &mut accounts.key_account,
+            &mut accounts.purpose_keys,
+            hash_eth_address(&key),
+            key_purpose,
+            KeyType::ECDSA,
+        )?;
+
+        emit!(EthKeyAdded {
+            manager,
+            key,
+            purpose,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_eth_key(_ctx: Context<RemoveEthKey>, key: [u8; 20], purpose: u32) -> Result<()> {
+        let key_context = &_ctx.accounts.key_context;
+        identity_require_auth(&key_context.identity_account, key_context.signer_key.as_ref(), &key_context.signer, KeyPurpose::Management)?;
+        let manager = key_context.signer.key();
+
+        let key_purpose = KeyPurpose::try_from(purpose)?;
+        let accounts = &mut *_ctx.accounts;
+        delete_key(
+            &mut accounts.key_context.identity_account,
+            &mut accounts.key_account,
+            &mut accounts.purpose_keys,
+            key_purpose,
+            &accounts.payer,
+        )?;
+
+        emit!(EthKeyRemoved {
+            manager,
+            key,
+            purpose,
+        });
+
+        Ok(())
+    }
+
+    /// Stores the claim at `[CLAIM_SEED, identity, claim_id]`, paid for by
+    /// `payer`, and lists it in the index of its topic.
+    ///
+    /// A claim with the same issuer and topic replaces the existing one, as in
+    /// ERC-735, emitting `ClaimChanged` instead of `ClaimAdded`.
+    #[allow(clippy::too_many_arguments)]
     pub fn add_claim(
         ctx: Context<ClaimContext>,
-        sender: Pubkey, 
         topic: u64,
         scheme: u64,
         issuer_wallet: Pubkey,
         issuer: Pubkey,
-        signature: [u8; 64],
+        signature: Vec<u8>,
         data: Vec<u8>,
         uri: String,
+        valid_from: i64,
+        valid_until: i64,
+        sig_ix_index: Option<u8>,
     ) -> Result<[u8; 32]> {
-        // identity_require_auth(&ctx.accounts.key_context.keys, &sender, KeyPurpose::Claim)?;
-
-        let current_program_id = ctx.program_id;
-        if *current_program_id != issuer {
+        let key_context = &ctx.accounts.key_context;
+        identity_require_auth(&key_context.identity_account, key_context.signer_key.as_ref(), &key_context.signer, KeyPurpose::Claim)?;
+        let sender = ctx.accounts.key_context.signer.key();
+
+        // Claims of a `claim_issuer` are checked by that issuer, revocations
+        // included, and those of an EVM claim issuer against its recorded Claim
+        // keys; any other claim must be self-issued, and needs no signature
+        // unless one is given
+        let identity = key_context.identity_account.key();
+        if let Some(claim_issuer_program) = &ctx.accounts.claim_issuer_program {
+            let is_valid = is_claim_valid_with_issuer(
+                claim_issuer_program,
+                ctx.accounts.issuer_keys.as_ref(),
+                ctx.accounts.issuer_revoked_claim.as_ref(),
+                &ctx.accounts.instructions,
+                issuer,
+                identity,
+                topic,
+                scheme,
+                issuer_wallet,
+                &signature,
+                &data,
+                valid_from,
+                valid_until,
+                sig_ix_index,
+            )?;
+            if !is_valid {
+                return Err(Error::InvalidClaim.into());
+            }
+        } else if let Some(evm_issuer) = &ctx.accounts.evm_issuer {
+            let is_valid = is_claim_valid_with_evm_issuer(
+                evm_issuer,
+                issuer_wallet,
+                identity,
+                topic,
+                scheme,
+                &signature,
+                &data,
+                valid_from,
+                valid_until,
+            )?;
+            if !is_valid {
+                return Err(Error::InvalidClaim.into());
+            }
+        } else if identity != issuer {
+            return Err(Error::InvalidIssuer.into());
+        } else if !signature.is_empty() {
+            let claim_key = ctx.accounts.claim_key.as_ref().ok_or(Error::KeyNotFound)?;
             let is_valid = is_claim_valid(
                 &ctx.accounts.instructions,
-                &ctx.accounts.key_context.keys,
+                claim_key,
                 issuer_wallet,
-                *current_program_id,
+                identity,
                 topic,
-                signature,
-                data.clone(),
+                scheme,
+                &signature,
+                &data,
+                valid_from,
+                valid_until,
+                sig_ix_index,
             )?;
             if !is_valid {
                 return Err(Error::InvalidClaim.into());
@@ -165,43 +323,90 @@ pub mod identity {
         }
     
         let claim_id = hash_claim(&issuer, topic);
-        let claims_account = &mut ctx.accounts.claims_account;
-        let new_claim = Claim {
+        let claim_index = &mut ctx.accounts.claim_index;
+        let is_replacement = claim_index.claim_ids.contains(&claim_id);
+        if !is_replacement {
+            if claim_index.claim_ids.len() >= ClaimTopicIndex::MAX_CLAIMS {
+                return Err(Error::TooManyClaims.into());
+            }
+            // The first claim on a topic pays for its index
+            if claim_index.claim_ids.is_empty() {
+                claim_index.bump = ctx.bumps.claim_index;
+                claim_index.topic = topic;
+                claim_index.payer = ctx.accounts.payer.key();
+            }
+            claim_index.claim_ids.push(claim_id);
+        }
+
+        let old_signature = ctx.accounts.claim.signature.clone();
+        // A replaced claim keeps the payer of its rent
+        let payer = if is_replacement { ctx.accounts.claim.payer } else { ctx.accounts.payer.key() };
+        ctx.accounts.claim.set_inner(Claim {
+            bump: ctx.bumps.claim,
             topic,
             scheme,
             issuer_wallet,
             issuer,
-            signature,
+            signature: signature.clone(),
             data: data.clone(),
             uri: uri.clone(),
-        };
-        claims_account.claims.push((claim_id, new_claim));
-    
-        emit!(ClaimAdded {
-            sender,
-            claim_id,
-            topic,
-            scheme,
-            issuer,
-            issuer_wallet,
-            signature,
-            data,
-            uri,
+            valid_from,
+            valid_until,
+            payer,
         });
     
+        if is_replacement {
+            emit!(ClaimChanged {
+                sender,
+                claim_id,
+                topic,
+                scheme,
+                issuer,
+                issuer_wallet,
+                old_signature,
+                signature,
+                data,
+                uri,
+                valid_from,
+                valid_until,
+            });
+        } else {
+            emit!(ClaimAdded {
+                sender,
+                claim_id,
+                topic,
+                scheme,
+                issuer,
+                issuer_wallet,
+                signature,
+                data,
+                uri,
+                valid_from,
+                valid_until,
+            });
+        }
+    
         Ok(claim_id)
     }
 
-    pub fn remove_claim(ctx: Context<ClaimContext>, sender: Pubkey, topic: u64, issuer: Pubkey) -> Result<()> {
+    /// Closes the claim, refunding its rent to `payer`, along with the index
+    /// of its topic once no claim is left in it.
+    pub fn remove_claim(ctx: Context<RemoveClaim>, topic: u64, issuer: Pubkey) -> Result<()> {
+        let key_context = &ctx.accounts.key_context;
+        identity_require_auth(&key_context.identity_account, key_context.signer_key.as_ref(), &key_context.signer, KeyPurpose::Claim)?;
+        let sender = ctx.accounts.key_context.signer.key();
         let claim_id = hash_claim(&issuer, topic);
-        // identity_require_auth(&ctx.accounts.key_context.keys, &sender, KeyPurpose::Claim)?;
 
-        let claims_account = &mut ctx.accounts.claims_account;
+        let claim_index = &mut ctx.accounts.claim_index;
 
-        let pos = claims_account.claims.iter().position(|(id, _)| *id == claim_id)
-            .ok_or(Error::ClaimNotFound)?;
+        if !claim_index.claim_ids.contains(&claim_id) {
+            return Err(Error::ClaimNotFound.into());
+        }
 
-        claims_account.claims.remove(pos);
+        claim_index.claim_ids.retain(|id| *id != claim_id);
+        if claim_index.claim_ids.is_empty() {
+            claim_index.close(ctx.accounts.index_payer.to_account_info())?;
+        }
 
         emit!(ClaimRemoved {
             sender,
@@ -211,22 +416,174 @@ pub mod identity {
         Ok(())
     }
 
-    pub fn get_claim(_ctx: Context<ClaimContext>, claim_id: [u8; 32]) -> Result<Option<Claim>> {
-        let claims_account = &_ctx.accounts.claims_account;
-        let claim = claims_account.claims.iter()
-            .find(|(id, _)| *id == claim_id)
-            .map(|(_, claim)| claim.clone());
-        Ok(claim)
+    pub fn get_claim(_ctx: Context<GetClaim>, _claim_id: [u8; 32]) -> Result<Claim> {
+        Ok(_ctx.accounts.claim.clone().into_inner())
     }
 
-    pub fn get_claim_ids(_ctx: Context<ClaimContext>) -> Result<Vec<[u8; 32]>> {
-        let claims_account = &_ctx.accounts.claims_account;
-        let claim_ids = claims_account.claims.iter()
-            .map(|(id, _)| *id)
-            .collect();
-        Ok(claim_ids)
+    /// ERC-735 `getClaimIdsByTopic`.
+    pub fn get_claim_ids(_ctx: Context<GetClaimIds>, _topic: u64) -> Result<Vec<[u8; 32]>> {
+        Ok(_ctx.accounts.claim_index.claim_ids.clone())
+    }
+
+    /// Requests an execution of `data` against `to`, signed by the identity (ERC-734).
+    ///
+    /// If the signer already holds the purpose required to approve it, the
+    /// execution is approved and run immediately, in which case the accounts of
+    /// the instruction, including the `to` program, must be passed as remaining accounts.
+    pub fn execute<'info>(
+        ctx: Context<'_, '_, '_, 'info, Execute<'info>>,
+        to: Pubkey,
+        accounts: Vec<ExecutionAccountMeta>,
+        data: Vec<u8>,
+    ) -> Result<u64> {
+        let identity_account = &mut ctx.accounts.identity_account;
+        let execution_id = identity_account.execution_nonce;
+        identity_account.execution_nonce += 1;
+
+        let execution = &mut ctx.accounts.execution;
+        execution.identity = identity_account.key();
+        execution.id = execution_id;
+        execution.to = to;
+        execution.accounts = accounts;
+        execution.data = data.clone();
+        execution.approvals = vec![];
+        execution.key_removals = identity_account.key_removals;
+        execution.approval_nonce = 0;
+        execution.executed = false;
+        execution.bump = ctx.bumps.execution;
+
+        emit!(ExecutionRequested {
+            execution_id,
+            to,
+            data,
+        });
+
+        let purpose = execution.required_purpose();
+        let key_hash = hash_key(&ctx.accounts.signer.key());
+        if ctx.accounts.signer_key.as_ref().is_some_and(|k| k.has_purpose(purpose)) {
+            execution.approvals.push(key_hash);
+            emit!(Approved {
+                execution_id,
+                key: key_hash,
+                approved: true,
+            });
+
+            if execution.approvals.len() >= identity_account.threshold(purpose) as usize {
+                // Persist the nonce before handing control to the target program.
+                identity_account.exit(ctx.program_id)?;
+                run_execution(identity_account, execution, ctx.remaining_accounts)?;
+                identity_account.reload()?;
+            }
+        }
+
+        Ok(execution_id)
+    }
+
+    /// Records the signer's approval of a pending execution, or withdraws it.
+    ///
+    /// Executions against the identity program require Management keys, any
+    /// other target requires Action keys. Once approvals from distinct keys
+    /// reach the purpose's threshold, the execution is run, signed by the
+    /// identity; the accounts of the instruction, including the `to` program,
+    /// must then be passed as remaining accounts.
+    pub fn approve<'info>(
+        ctx: Context<'_, '_, '_, 'info, Approve<'info>>,
+        _execution_id: u64,
+        approve: bool,
+    ) -> Result<()> {
+        record_approval(
+            &mut ctx.accounts.identity_account,
+            &ctx.accounts.signer_key,
+            &mut ctx.accounts.execution,
+            approve,
+            ctx.remaining_accounts,
+        )
+    }
+
+    /// Same as `approve`, for an Ethereum-address key.
+    ///
+    /// Ownership of the key is proven by a `Secp256k1Program` instruction at
+    /// `ix_index` in the same transaction, verifying `signature` by `eth_address`
+    /// over the `personal_sign` (EIP-191) form of `approval_message`, which
+    /// covers the execution's current `approval_nonce`.
+    pub fn approve_with_eth_key<'info>(
+        ctx: Context<'_, '_, '_, 'info, ApproveWithEthKey<'info>>,
+        execution_id: u64,
+        approve: bool,
+        eth_address: [u8; 20],
+        signature: Vec<u8>,
+        ix_index: u8,
+    ) -> Result<()> {
+        // Signatures made before a key removal must not outlive the approvals it dropped
+        drop_stale_approvals(&ctx.accounts.identity_account, &mut ctx.accounts.execution);
+
+        let ix: Instruction = load_instruction_at_checked(ix_index as usize, &ctx.accounts.instructions)?;
+        let msg = approval_message(
+            &ctx.accounts.identity_account.key(),
+            execution_id,
+            ctx.accounts.execution.approval_nonce,
+            approve,
+        );
+        verify_secp256k1_ix(&ix, ix_index, &eth_address, &eth_signed_message(&msg), &signature)?;
+
+        record_approval(
+            &mut ctx.accounts.identity_account,
+            &ctx.accounts.eth_key,
+            &mut ctx.accounts.execution,
+            approve,
+            ctx.remaining_accounts,
+        )
+    }
+
+    /// Sets how many distinct keys must approve executions and key changes for
+    /// `purpose` (Management or Action).
+    pub fn set_threshold(ctx: Context<SetThreshold>, purpose: u32, threshold: u8) -> Result<()> {
+        let accounts = &ctx.accounts;
+        identity_require_auth(&accounts.identity_account, accounts.signer_key.as_ref(), &accounts.signer, KeyPurpose::Management)?;
+
+        let key_purpose = KeyPurpose::try_from(purpose)?;
+        if threshold == 0 || accounts.purpose_keys.keys.len() < threshold as usize {
+            return Err(Error::InvalidThreshold.into());
+        }
+
+        let identity_account = &mut ctx.accounts.identity_account;
+        match key_purpose {
+            KeyPurpose::Management => identity_account.management_threshold = threshold,
+            KeyPurpose::Action => identity_account.action_threshold = threshold,
+            _ => return Err(Error::InvalidKeyPurpose.into()),
+        }
+
+        emit!(ThresholdSet {
+            purpose,
+            threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Records the Ethereum addresses of the Claim keys of the EVM claim issuer
+    /// `issuer` for the identities of the signing factory. The ECDSA claims
+    /// that issuer signs are checked against them, and an empty list stops
+    /// any from being accepted.
+    pub fn set_evm_issuer(ctx: Context<SetEvmIssuer>, issuer: Pubkey, claim_keys: Vec<[u8; 20]>) -> Result<()> {
+        if claim_keys.len() > EvmIssuer::MAX_CLAIM_KEYS {
+            return Err(Error::TooManyKeys.into());
+        }
+
+        ctx.accounts.evm_issuer.set_inner(EvmIssuer {
+            bump: ctx.bumps.evm_issuer,
+            issuer,
+            claim_keys: claim_keys.clone(),
+        });
+
+        emit!(EvmIssuerSet {
+            factory: ctx.accounts.factory.key(),
+            issuer,
+            claim_keys,
+        });
+
+        Ok(())
     }
-    
 }
 
 #[event]
@@ -244,6 +601,20 @@ pub struct KeyRemoved {
     pub purpose: u32,
 }
 
+#[event]
+pub struct EthKeyAdded {
+    pub manager: Pubkey,
+    pub key: [u8; 20],
+    pub purpose: u32,
+}
+
+#[event]
+pub struct EthKeyRemoved {
+    pub manager: Pubkey,
+    pub key: [u8; 20],
+    pub purpose: u32,
+}
+
 #[event]
 pub struct ClaimAdded {
     pub sender: Pubkey,
@@ -252,9 +623,27 @@ pub struct ClaimAdded {
     pub scheme: u64,
     pub issuer: Pubkey,
     pub issuer_wallet: Pubkey,
-    pub signature: [u8; 64],
+    pub signature: Vec<u8>,
+    pub data: Vec<u8>,
+    pub uri: String,
+    pub valid_from: i64,
+    pub valid_until: i64,
+}
+
+#[event]
+pub struct ClaimChanged {
+    pub sender: Pubkey,
+    pub claim_id: [u8; 32],
+    pub topic: u64,
+    pub scheme: u64,
+    pub issuer: Pubkey,
+    pub issuer_wallet: Pubkey,
+    pub old_signature: Vec<u8>,
+    pub signature: Vec<u8>,
     pub data: Vec<u8>,
     pub uri: String,
+    pub valid_from: i64,
+    pub valid_until: i64,
 }
 
 #[event]
@@ -263,28 +652,369 @@ pub struct ClaimRemoved {
     pub claim_id: [u8; 32],
 }
 
+#[event]
+pub struct ExecutionRequested {
+    pub execution_id: u64,
+    pub to: Pubkey,
+    pub data: Vec<u8>,
+}
 
-// fn identity_require_auth(keys_account: &Account<KeysAccount>, sender: &Pubkey, key_type: KeyPurpose) -> Result<()> {
-//     let key_hash = hash_key(sender);
+#[event]
+pub struct Approved {
+    pub execution_id: u64,
+    pub key: [u8; 32],
+    pub approved: bool,
+}
 
-//     if !key_has_purpose(keys_account, &key_hash, key_type) {
-//         return Err(Error::InsufficientPermissions.into());
-//     }
+#[event]
+pub struct ThresholdSet {
+    pub purpose: u32,
+    pub threshold: u8,
+}
 
-//     Ok(())
-// }
+#[event]
+pub struct Executed {
+    pub execution_id: u64,
+    pub to: Pubkey,
+    pub data: Vec<u8>,
+}
 
-fn key_has_purpose(keys_account: &Account<KeysAccount>, key_hash: &[u8; 32], purpose: KeyPurpose) -> bool {
-    keys_account.keys.iter().any(|k| k.key == *key_hash && k.purposes.contains(&purpose))
+#[event]
+pub struct EvmIssuerSet {
+    pub factory: Pubkey,
+    pub issuer: Pubkey,
+    pub claim_keys: Vec<[u8; 20]>,
 }
 
-fn hash_key(key: &Pubkey) -> [u8; 32] {
+
+/// Authorizes `signer` for `purpose` on the identity.
+///
+/// The factory recorded in the identity account is allowed when it has
+/// management rights, so that it can manage identities on behalf of bridged
+/// requests via CPI. When the
+/// purpose has an approval threshold above one, a single key is not enough and
+/// the operation has to go through an approved execution instead.
+fn identity_require_auth(
+    identity_account: &Account<IdentityAccount>,
+    signer_key: Option<&Account<KeyAccount>>,
+    signer: &Signer,
+    purpose: KeyPurpose,
+) -> Result<()> {
+    let signer = signer.key();
+
+    if identity_account.factory_is_manager && signer == identity_account.factory {
+        return Ok(());
+    }
+
+    // The identity itself, running an approved execution.
+    if signer == identity_account.key() {
+        return Ok(());
+    }
+
+    if !signer_key.is_some_and(|k| k.has_purpose(purpose)) {
+        return Err(Error::InsufficientPermissions.into());
+    }
+
+    if identity_account.threshold(purpose) > 1 {
+        return Err(Error::ApprovalThresholdNotMet.into());
+    }
+
+    Ok(())
+}
+
+fn setup_identity(
+    identity_account: &mut Account<IdentityAccount>,
+    factory: Pubkey,
+    factory_is_manager: bool,
+    wallet: Pubkey,
+    salt: [u8; 32],
+    bump: u8,
+) {
+    identity_account.initialized = true;
+    identity_account.factory = factory;
+    identity_account.factory_is_manager = factory_is_manager;
+    identity_account.wallet = wallet;
+    identity_account.salt = salt;
+    identity_account.bump = bump;
+    identity_account.management_threshold = 1;
+    identity_account.action_threshold = 1;
+
+    msg!("Identity {:?} initialized for wallet {:?} with salt {:?}", identity_account.key(), wallet, salt);
+}
+
+/// Creates `key` at its `[KEY_SEED, identity, key_hash]` PDA, `key_info`.
+fn create_key_account<'info>(
+    key_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    mut key: KeyAccount,
+) -> Result<()> {
+    let (key_address, bump) = Pubkey::find_program_address(&[KEY_SEED, key.identity.as_ref(), &key.key], &crate::ID);
+    if key_info.key() != key_address {
+        return Err(Error::InvalidKeyAccount.into());
+    }
+    key.bump = bump;
+
+    let seeds: &[&[u8]] = &[KEY_SEED, key.identity.as_ref(), &key.key, &[bump]];
+    create_pda(key_info, payer, system_program, seeds, KeyAccount::LEN)?;
+
+    key.try_serialize(&mut &mut key_info.try_borrow_mut_data()?[..])
+}
+
+/// Creates the PDA signed for by `seeds` at `account`, owned by this program.
+/// Anyone can send lamports to a key's address before the key is added, so an
+/// address that already holds some is topped up, allocated and assigned the
+/// way Anchor's `init` does.
+fn create_pda<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    space: usize,
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    if account.lamports() == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                &[seeds],
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    let top_up = rent.saturating_sub(account.lamports());
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Allocate { account_to_allocate: account.clone() },
+            &[seeds],
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Assign { account_to_assign: account.clone() },
+            &[seeds],
+        ),
+        &crate::ID,
+    )
+}
+
+fn insert_key(
+    identity: Pubkey,
+    key_account: &mut Account<KeyAccount>,
+    purpose_keys: &mut Account<PurposeKeysAccount>,
+    key_hash: [u8; 32],
+    key_purpose: KeyPurpose,
+    key_type: KeyType,
+) -> Result<()> {
+    if key_account.has_purpose(key_purpose) {
+        return Err(Error::KeyConflict.into());
+    }
+
+    // A key without purposes was just created
+    if key_account.purposes == 0 {
+        key_account.identity = identity;
+        key_account.key = key_hash;
+        key_account.key_type = key_type;
+    }
+    key_account.purposes |= key_purpose.mask();
+
+    if purpose_keys.keys.len() >= PurposeKeysAccount::MAX_KEYS {
+        return Err(Error::TooManyKeys.into());
+    }
+    purpose_keys.purpose = key_purpose as u32;
+    purpose_keys.keys.push(key_hash);
+
+    Ok(())
+}
+
+fn delete_key<'info>(
+    identity_account: &mut Account<'info, IdentityAccount>,
+    key_account: &mut Account<'info, KeyAccount>,
+    purpose_keys: &mut Account<'info, PurposeKeysAccount>,
+    key_purpose: KeyPurpose,
+    payer: &Signer<'info>,
+) -> Result<()> {
+    if !key_account.has_purpose(key_purpose) {
+        return Err(Error::KeyDoesNotHavePurpose.into());
+    }
+    key_account.purposes &= !key_purpose.mask();
+
+    if let Some(pos) = purpose_keys.keys.iter().position(|k| *k == key_account.key) {
+        purpose_keys.keys.remove(pos);
+    }
+
+    // The identity keeps a Management key, and enough keys to reach a
+    // threshold above one; other purposes can lose their last key
+    let threshold = identity_account.threshold(key_purpose);
+    if (key_purpose == KeyPurpose::Management || threshold > 1) && purpose_keys.keys.len() < threshold as usize {
+        return Err(Error::InvalidThreshold.into());
+    }
+
+    if key_account.purposes == 0 {
+        key_account.close(payer.to_account_info())?;
+    }
+    identity_account.key_removals += 1;
+
+    Ok(())
+}
+
+/// Records or withdraws the approval of `key_hash`, and runs the execution
+/// once the threshold of its purpose is reached.
+///
+/// Approvals collected before a key of the identity was last removed are
+/// dropped, so that removed keys never count towards the threshold.
+fn record_approval<'info>(
+    identity_account: &mut Account<'info, IdentityAccount>,
+    key_account: &Account<'info, KeyAccount>,
+    execution: &mut Account<'info, Execution>,
+    approve: bool,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    if execution.executed {
+        return Err(Error::ExecutionAlreadyExecuted.into());
+    }
+
+    let purpose = execution.required_purpose();
+    if !key_account.has_purpose(purpose) {
+        return Err(Error::InsufficientPermissions.into());
+    }
+    let key_hash = key_account.key;
+
+    drop_stale_approvals(identity_account, execution);
+
+    let position = execution.approvals.iter().position(|k| *k == key_hash);
+    if approve {
+        if position.is_some() {
+            return Err(Error::AlreadyApproved.into());
+        }
+        execution.approvals.push(key_hash);
+    } else if let Some(pos) = position {
+        execution.approvals.remove(pos);
+        execution.approval_nonce += 1;
+    }
+
+    emit!(Approved {
+        execution_id: execution.id,
+        key: key_hash,
+        approved: approve,
+    });
+
+    if approve && execution.approvals.len() >= identity_account.threshold(purpose) as usize {
+        run_execution(identity_account, execution, remaining_accounts)?;
+        // The execution may have changed the identity, e.g. removed a key.
+        identity_account.reload()?;
+    }
+
+    Ok(())
+}
+
+/// Drops the approvals of `execution` collected before a key of the identity
+/// was last removed, moving on to a new approval nonce.
+fn drop_stale_approvals(identity_account: &IdentityAccount, execution: &mut Execution) {
+    if execution.key_removals != identity_account.key_removals {
+        execution.approvals.clear();
+        execution.key_removals = identity_account.key_removals;
+        execution.approval_nonce += 1;
+    }
+}
+
+/// Invokes an approved execution with the identity PDA as signer.
+fn run_execution<'info>(
+    identity_account: &Account<'info, IdentityAccount>,
+    execution: &mut Account<'info, Execution>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    // Mark the execution as done before the CPI so it cannot be replayed.
+    execution.executed = true;
+    execution.exit(&crate::ID)?;
+
+    let instruction = Instruction {
+        program_id: execution.to,
+        accounts: execution.accounts.iter()
+            .map(|a| AccountMeta {
+                pubkey: a.pubkey,
+                is_signer: a.is_signer,
+                is_writable: a.is_writable,
+            })
+            .collect(),
+        data: execution.data.clone(),
+    };
+
+    let mut account_infos = remaining_accounts.to_vec();
+    account_infos.push(identity_account.to_account_info());
+
+    let seeds: &[&[u8]] = &[
+        IDENTITY_SEED,
+        identity_account.factory.as_ref(),
+        identity_account.wallet.as_ref(),
+        identity_account.salt.as_ref(),
+        &[identity_account.bump],
+    ];
+    invoke_signed(&instruction, &account_infos, &[seeds])?;
+
+    emit!(Executed {
+        execution_id: execution.id,
+        to: execution.to,
+        data: execution.data.clone(),
+    });
+
+    Ok(())
+}
+
+pub fn hash_key(key: &Pubkey) -> [u8; 32] {
     let key_bytes = key.to_bytes();
     let hashed = hash(&key_bytes);
 
     hashed.to_bytes()
 }
 
+/// ERC-734 key hash of an Ethereum address, `keccak256(abi.encode(address))`.
+pub fn hash_eth_address(address: &[u8; 20]) -> [u8; 32] {
+    keccak::hash(&eth_address_pubkey(address).to_bytes()).to_bytes()
+}
+
+/// An Ethereum address as a `Pubkey`, left-padded like `abi.encode(address)`;
+/// the `issuer_wallet` of claims signed by Ethereum-address keys.
+pub fn eth_address_pubkey(address: &[u8; 20]) -> Pubkey {
+    let mut bytes = [0u8; 32];
+    bytes[12..].copy_from_slice(address);
+
+    Pubkey::new_from_array(bytes)
+}
+
+/// Message an Ethereum-address key signs to approve or reject an execution.
+pub fn approval_message(identity: &Pubkey, execution_id: u64, approval_nonce: u64, approve: bool) -> Vec<u8> {
+    let mut message = Vec::new();
+    message.extend_from_slice(&identity.to_bytes());
+    message.extend_from_slice(&execution_id.to_le_bytes());
+    message.extend_from_slice(&approval_nonce.to_le_bytes());
+    message.push(approve as u8);
+
+    message
+}
+
 pub fn hash_claim(issuer: &Pubkey, topic: u64) -> [u8; 32] {
     let issuer_bytes = issuer.to_bytes();
 
@@ -300,81 +1030,621 @@ pub fn hash_claim(issuer: &Pubkey, topic: u64) -> [u8; 32] {
 }
 
 
+/// Checks the signature of a self-issued claim according to its `scheme`.
+///
+/// Ed25519 claims are signed by `issuer_wallet` over `claim_issuer::claim_message`
+/// and verified through an `Ed25519Program` instruction of the transaction.
+/// The instruction is optionally located by `sig_ix_index`. ECDSA claims are
+/// signed by the Ethereum address `issuer_wallet`, see `ecdsa_claim_signer`.
+/// Either way the signing key must hold the Claim purpose, which `claim_key`,
+/// the identity's key account of the signer, is checked for.
+///
+/// A validity window, when set, is covered by the signed message and the claim
+/// is only valid while the clock is within it.
+#[allow(clippy::too_many_arguments)]
 pub fn is_claim_valid(
     instructions: &AccountInfo,
-    keys: &Account<KeysAccount>,
+    claim_key: &AccountInfo,
     issuer_wallet: Pubkey,
     identity: Pubkey,
     topic: u64,
-    signature: [u8; 64],
-    data: Vec<u8>,
+    scheme: u64,
+    signature: &[u8],
+    data: &[u8],
+    valid_from: i64,
+    valid_until: i64,
+    sig_ix_index: Option<u8>,
 ) -> Result<bool> {
-    let mut concatenated_bytes = Vec::new();
-    concatenated_bytes.extend_from_slice(&identity.to_bytes());
-    concatenated_bytes.extend_from_slice(&topic.to_le_bytes());
-    concatenated_bytes.extend_from_slice(&data);
-
-    // Verify the Ed25519 signature
-    let instruction_sysvar_info = instructions.to_account_info();
-    let ix: Instruction = load_instruction_at_checked(0, &instruction_sysvar_info)?;
-
-    if ix.program_id != ED25519_ID {
-        return Err(Error::InvalidSignature.into());
+    if !is_within_validity(valid_from, valid_until)? {
+        return Err(Error::ClaimExpired.into());
     }
 
-    verify_ed25519_ix(&ix, &issuer_wallet.to_bytes(), &concatenated_bytes, &signature)?;
+    let signer_key_hash = match ClaimScheme::try_from(scheme)? {
+        ClaimScheme::Ed25519 => {
+            // Verify the Ed25519 signature
+            verify_ed25519_in_tx(
+                instructions,
+                sig_ix_index,
+                &issuer_wallet.to_bytes(),
+                &claim_message(&identity, topic, data, valid_from, valid_until),
+                signature,
+            )?;
+
+            hash_key(&issuer_wallet)
+        }
+        ClaimScheme::Ecdsa => {
+            let eth_address = ecdsa_claim_signer(issuer_wallet, identity, topic, signature, data, valid_from, valid_until)?;
+            hash_eth_address(&eth_address)
+        }
+    };
 
-    let issuer_wallet_hash = hash_key(&issuer_wallet);
-    if key_has_purpose(keys, &issuer_wallet_hash, KeyPurpose::Claim) {
+    if *claim_key.owner != crate::ID {
+        return Err(Error::KeyNotFound.into());
+    }
+    let claim_key = KeyAccount::try_deserialize(&mut &claim_key.try_borrow_data()?[..])?;
+    if claim_key.identity == identity && claim_key.key == signer_key_hash && claim_key.has_purpose(KeyPurpose::Claim) {
         Ok(true)
     } else {
         Err(Error::InvalidClaim.into())
     }
 }
 
+/// Checks an ECDSA claim signed by an EVM claim issuer against the Claim keys
+/// recorded for it in `evm_issuer`.
+#[allow(clippy::too_many_arguments)]
+pub fn is_claim_valid_with_evm_issuer(
+    evm_issuer: &EvmIssuer,
+    issuer_wallet: Pubkey,
+    identity: Pubkey,
+    topic: u64,
+    scheme: u64,
+    signature: &[u8],
+    data: &[u8],
+    valid_from: i64,
+    valid_until: i64,
+) -> Result<bool> {
+    // EVM claim issuers only sign with ECDSA keys
+    if ClaimScheme::try_from(scheme)? != ClaimScheme::Ecdsa {
+        return Err(Error::InvalidClaimScheme.into());
+    }
+    if !is_within_validity(valid_from, valid_until)? {
+        return Err(Error::ClaimExpired.into());
+    }
+
+    let eth_address = ecdsa_claim_signer(issuer_wallet, identity, topic, signature, data, valid_from, valid_until)?;
+    Ok(evm_issuer.claim_keys.contains(&eth_address))
+}
+
+/// The Ethereum address that signed an ECDSA claim, which must be `issuer_wallet`
+/// (see `eth_address_pubkey`). Such claims are signed like EVM claims, over the
+/// `personal_sign` form of `keccak256(abi.encode(identity, topic, data))`, and
+/// the signer is recovered through the `secp256k1_recover` syscall, so that
+/// claims bridged by the factory need no Secp256k1Program instruction.
+#[allow(clippy::too_many_arguments)]
+fn ecdsa_claim_signer(
+    issuer_wallet: Pubkey,
+    identity: Pubkey,
+    topic: u64,
+    signature: &[u8],
+    data: &[u8],
+    valid_from: i64,
+    valid_until: i64,
+) -> Result<[u8; 20]> {
+    let eth_address: [u8; 20] = issuer_wallet.to_bytes()[12..].try_into().unwrap();
+    if issuer_wallet != eth_address_pubkey(&eth_address) {
+        return Err(Error::InvalidIssuer.into());
+    }
+
+    let window = validity_window(valid_from, valid_until);
+    let data_hash = keccak::hash(&abi_encode_claim(&identity, topic, data, window)).to_bytes();
+    let message_hash = keccak::hash(&eth_signed_message(&data_hash)).to_bytes();
+    if recover_eth_address(&message_hash, signature)? != eth_address {
+        return Err(error::ErrorCode::SigVerificationFailed.into());
+    }
+
+    Ok(eth_address)
+}
+
+/// Asks a `claim_issuer` whether it still vouches for a claim, through its
+/// read-only `is_claim_valid` instruction. The issuer of such claims is the
+/// address of the claim issuer's keys account.
+#[allow(clippy::too_many_arguments)]
+pub fn is_claim_valid_with_issuer<'info>(
+    claim_issuer_program: &Program<'info, ClaimIssuer>,
+    issuer_keys: Option<&UncheckedAccount<'info>>,
+    issuer_revoked_claim: Option<&UncheckedAccount<'info>>,
+    instructions: &AccountInfo<'info>,
+    issuer: Pubkey,
+    identity: Pubkey,
+    topic: u64,
+    scheme: u64,
+    issuer_wallet: Pubkey,
+    signature: &[u8],
+    data: &[u8],
+    valid_from: i64,
+    valid_until: i64,
+    ed25519_ix_index: Option<u8>,
+) -> Result<bool> {
+    let (Some(issuer_keys), Some(issuer_revoked_claim)) = (issuer_keys, issuer_revoked_claim) else {
+        return Err(Error::InvalidIssuer.into());
+    };
+    require_keys_eq!(issuer_keys.key(), issuer, Error::InvalidIssuer);
+
+    // Claim issuers only sign with Ed25519 keys
+    if ClaimScheme::try_from(scheme)? != ClaimScheme::Ed25519 {
+        return Err(Error::InvalidClaimScheme.into());
+    }
+    let signature: [u8; 64] = signature.try_into().map_err(|_| Error::InvalidSignature)?;
+
+    let is_valid = claim_issuer_lib::cpi::is_claim_valid(
+        CpiContext::new(
+            claim_issuer_program.to_account_info(),
+            claim_issuer_lib::cpi::accounts::IsClaimValid {
+                keys: issuer_keys.to_account_info(),
+                revoked_claim: issuer_revoked_claim.to_account_info(),
+                instructions: instructions.clone(),
+            },
+        ),
+        identity,
+        topic,
+        issuer_wallet,
+        signature,
+        data.to_vec(),
+        valid_from,
+        valid_until,
+        ed25519_ix_index,
+    )?
+    .get();
+
+    Ok(is_valid)
+}
+
+/// `0` leaves either end of a claim's validity window open; a claim with
+/// neither end set has no window at all.
+fn validity_window(valid_from: i64, valid_until: i64) -> Option<(i64, i64)> {
+    if valid_from == 0 && valid_until == 0 {
+        None
+    } else {
+        Some((valid_from, valid_until))
+    }
+}
+
+/// Whether the clock is within a claim's validity window.
+pub fn is_within_validity(valid_from: i64, valid_until: i64) -> Result<bool> {
+    let now = Clock::get()?.unix_timestamp;
+    Ok((valid_from == 0 || now >= valid_from) && (valid_until == 0 || now < valid_until))
+}
+
+/// `abi.encode(identity, topic, data)`, with the identity as a 32-byte word,
+/// or `abi.encode(identity, topic, data, validFrom, validUntil)` for claims
+/// with a validity window.
+fn abi_encode_claim(identity: &Pubkey, topic: u64, data: &[u8], window: Option<(i64, i64)>) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    encoded.extend_from_slice(&identity.to_bytes());
+
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&topic.to_be_bytes());
+    encoded.extend_from_slice(&word);
+
+    // Offset of the dynamic `data` argument, after the head words
+    let head_words: u64 = if window.is_some() { 5 } else { 3 };
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&(head_words * 32).to_be_bytes());
+    encoded.extend_from_slice(&word);
+
+    // `int256` words, sign-extended
+    if let Some((valid_from, valid_until)) = window {
+        for timestamp in [valid_from, valid_until] {
+            let mut word = if timestamp < 0 { [0xffu8; 32] } else { [0u8; 32] };
+            word[24..].copy_from_slice(&timestamp.to_be_bytes());
+            encoded.extend_from_slice(&word);
+        }
+    }
+
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&(data.len() as u64).to_be_bytes());
+    encoded.extend_from_slice(&word);
+
+    encoded.extend_from_slice(data);
+    encoded.resize(encoded.len() + (32 - data.len() % 32) % 32, 0);
+
+    encoded
+}
+
 
 /// External instruction that only gets executed if
 /// an `Ed25519Program.createInstructionWithPublicKey`
 /// instruction was sent in the same transaction.
 pub fn verify_ed25519(ctx: Context<Verify>, pubkey: [u8; 32], msg: Vec<u8>, sig: [u8; 64]) -> Result<()> {
-    // Get what should be the Ed25519Program instruction
-    let ix: Instruction = load_instruction_at_checked(0, &ctx.accounts.ix_sysvar).map_err(|e| anchor_lang::prelude::ProgramError::from(e))?;
-
-    // Check that ix is what we expect to have been sent
-    utils::verify_ed25519_ix(&ix, &pubkey, &msg, &sig)?;
+    // Check that an Ed25519Program instruction of the transaction verified the signature
+    utils::verify_ed25519_in_tx(&ctx.accounts.ix_sysvar, None, &pubkey, &msg, &sig)?;
     
     Ok(())
 }
 
 #[derive(Accounts)]
+#[instruction(wallet: Pubkey, salt: [u8; 32], initial_management_key: Pubkey)]
 pub struct Initialize<'info> {
-    #[account(init, payer = user, space = 8 + 1)] // 8 bytes for discriminator, 1 for bool
+    #[account(
+        init,
+        payer = user,
+        space = IdentityAccount::LEN,
+        seeds = [IDENTITY_SEED, factory.key().as_ref(), wallet.as_ref(), salt.as_ref()],
+        bump
+    )]
+    pub identity_account: Account<'info, IdentityAccount>,
+    #[account(
+        init,
+        payer = user,
+        space = KeyAccount::LEN,
+        seeds = [KEY_SEED, identity_account.key().as_ref(), &hash_key(&initial_management_key)],
+        bump
+    )]
+    pub management_key: Account<'info, KeyAccount>,
+    #[account(
+        init,
+        payer = user,
+        space = PurposeKeysAccount::LEN,
+        seeds = [KEYS_SEED, identity_account.key().as_ref(), &(KeyPurpose::Management as u32).to_le_bytes()],
+        bump
+    )]
+    pub management_keys: Account<'info, PurposeKeysAccount>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// The factory deploying the identity, signing through its PDA.
+    pub factory: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey, salt: [u8; 32])]
+pub struct InitializeWithManagementKeys<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = IdentityAccount::LEN,
+        seeds = [IDENTITY_SEED, factory.key().as_ref(), wallet.as_ref(), salt.as_ref()],
+        bump
+    )]
     pub identity_account: Account<'info, IdentityAccount>,
-    #[account(init, payer = user, space = NewKey::LEN + 1)] // 8 bytes for discriminator, 1 for bool
-    pub key_account: Account<'info, NewKey>,
-    pub keys: Account<'info, KeysAccount>,
-    #[account(init, payer = user, space = Claim::LEN + 8)]
-    pub claims_account: Account<'info, ClaimsAccount>,
+    #[account(
+        init,
+        payer = user,
+        space = PurposeKeysAccount::LEN,
+        seeds = [KEYS_SEED, identity_account.key().as_ref(), &(KeyPurpose::Management as u32).to_le_bytes()],
+        bump
+    )]
+    pub management_keys: Account<'info, PurposeKeysAccount>,
     #[account(mut)]
     pub user: Signer<'info>,
     pub system_program: Program<'info, System>,
-    pub factory: AccountInfo<'info>,
+    /// The factory deploying the identity, signing through its PDA.
+    pub factory: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetIdentity<'info> {
+    pub identity_account: Account<'info, IdentityAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(to: Pubkey, accounts: Vec<ExecutionAccountMeta>, data: Vec<u8>)]
+pub struct Execute<'info> {
+    #[account(mut)]
+    pub identity_account: Account<'info, IdentityAccount>,
+    /// The signer's key, approving the execution right away if it holds the required purpose.
+    #[account(
+        seeds = [KEY_SEED, identity_account.key().as_ref(), &hash_key(&signer.key())],
+        bump = signer_key.bump
+    )]
+    pub signer_key: Option<Account<'info, KeyAccount>>,
+    #[account(
+        init,
+        payer = signer,
+        space = Execution::space(accounts.len(), data.len()),
+        seeds = [EXECUTION_SEED, identity_account.key().as_ref(), &identity_account.execution_nonce.to_le_bytes()],
+        bump
+    )]
+    pub execution: Account<'info, Execution>,
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(execution_id: u64)]
+pub struct Approve<'info> {
+    #[account(mut)]
+    pub identity_account: Account<'info, IdentityAccount>,
+    #[account(
+        seeds = [KEY_SEED, identity_account.key().as_ref(), &hash_key(&signer.key())],
+        bump = signer_key.bump
+    )]
+    pub signer_key: Account<'info, KeyAccount>,
+    #[account(
+        mut,
+        seeds = [EXECUTION_SEED, identity_account.key().as_ref(), &execution_id.to_le_bytes()],
+        bump = execution.bump
+    )]
+    pub execution: Account<'info, Execution>,
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(execution_id: u64, approve: bool, eth_address: [u8; 20])]
+pub struct ApproveWithEthKey<'info> {
+    #[account(mut)]
+    pub identity_account: Account<'info, IdentityAccount>,
+    #[account(
+        seeds = [KEY_SEED, identity_account.key().as_ref(), &hash_eth_address(&eth_address)],
+        bump = eth_key.bump
+    )]
+    pub eth_key: Account<'info, KeyAccount>,
+    #[account(
+        mut,
+        seeds = [EXECUTION_SEED, identity_account.key().as_ref(), &execution_id.to_le_bytes()],
+        bump = execution.bump
+    )]
+    pub execution: Account<'info, Execution>,
+    /// CHECK: The address check is needed because otherwise
+    /// the supplied Sysvar could be anything else.
+    #[account(address = IX_ID)]
+    pub instructions: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(purpose: u32)]
+pub struct SetThreshold<'info> {
+    #[account(mut)]
+    pub identity_account: Account<'info, IdentityAccount>,
+    #[account(
+        seeds = [KEY_SEED, identity_account.key().as_ref(), &hash_key(&signer.key())],
+        bump = signer_key.bump
+    )]
+    pub signer_key: Option<Account<'info, KeyAccount>>,
+    #[account(
+        seeds = [KEYS_SEED, identity_account.key().as_ref(), &purpose.to_le_bytes()],
+        bump = purpose_keys.bump
+    )]
+    pub purpose_keys: Account<'info, PurposeKeysAccount>,
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(issuer: Pubkey)]
+pub struct SetEvmIssuer<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = EvmIssuer::LEN,
+        seeds = [EVM_ISSUER_SEED, factory.key().as_ref(), issuer.as_ref()],
+        bump
+    )]
+    pub evm_issuer: Account<'info, EvmIssuer>,
+    /// The factory whose identities accept the issuer's claims, signing through its PDA.
+    pub factory: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct _Key<'info> {
     #[account(mut)]
-    pub keys: Account<'info, KeysAccount>,
+    pub identity_account: Account<'info, IdentityAccount>,
+    /// The signer's key; absent when the signer is the identity's factory or
+    /// the identity itself.
+    #[account(
+        seeds = [KEY_SEED, identity_account.key().as_ref(), &hash_key(&signer.key())],
+        bump = signer_key.bump
+    )]
+    pub signer_key: Option<Account<'info, KeyAccount>>,
+    /// Either a key holding the required purpose, or the identity's factory.
+    pub signer: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimContext<'info> {
+#[instruction(key: Pubkey, purpose: u32)]
+pub struct AddKey<'info> {
+    pub key_context: _Key<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = KeyAccount::LEN,
+        seeds = [KEY_SEED, key_context.identity_account.key().as_ref(), &hash_key(&key)],
+        bump
+    )]
+    pub key_account: Account<'info, KeyAccount>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PurposeKeysAccount::LEN,
+        seeds = [KEYS_SEED, key_context.identity_account.key().as_ref(), &purpose.to_le_bytes()],
+        bump
+    )]
+    pub purpose_keys: Account<'info, PurposeKeysAccount>,
     #[account(mut)]
-    pub claims_account: Account<'info, ClaimsAccount>,
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(key: [u8; 20], purpose: u32)]
+pub struct AddEthKey<'info> {
+    pub key_context: _Key<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = KeyAccount::LEN,
+        seeds = [KEY_SEED, key_context.identity_account.key().as_ref(), &hash_eth_address(&key)],
+        bump
+    )]
+    pub key_account: Account<'info, KeyAccount>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PurposeKeysAccount::LEN,
+        seeds = [KEYS_SEED, key_context.identity_account.key().as_ref(), &purpose.to_le_bytes()],
+        bump
+    )]
+    pub purpose_keys: Account<'info, PurposeKeysAccount>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(key: Pubkey, purpose: u32)]
+pub struct RemoveKey<'info> {
+    pub key_context: _Key<'info>,
+    #[account(
+        mut,
+        seeds = [KEY_SEED, key_context.identity_account.key().as_ref(), &hash_key(&key)],
+        bump = key_account.bump
+    )]
+    pub key_account: Account<'info, KeyAccount>,
+    #[account(
+        mut,
+        seeds = [KEYS_SEED, key_context.identity_account.key().as_ref(), &purpose.to_le_bytes()],
+        bump = purpose_keys.bump
+    )]
+    pub purpose_keys: Account<'info, PurposeKeysAccount>,
+    /// Receives the rent of the key once it has no purpose left.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(key: [u8; 20], purpose: u32)]
+pub struct RemoveEthKey<'info> {
+    pub key_context: _Key<'info>,
+    #[account(
+        mut,
+        seeds = [KEY_SEED, key_context.identity_account.key().as_ref(), &hash_eth_address(&key)],
+        bump = key_account.bump
+    )]
+    pub key_account: Account<'info, KeyAccount>,
+    #[account(
+        mut,
+        seeds = [KEYS_SEED, key_context.identity_account.key().as_ref(), &purpose.to_le_bytes()],
+        bump = purpose_keys.bump
+    )]
+    pub purpose_keys: Account<'info, PurposeKeysAccount>,
+    /// Receives the rent of the key once it has no purpose left.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(key: Pubkey)]
+pub struct GetKey<'info> {
+    pub identity_account: Account<'info, IdentityAccount>,
+    #[account(
+        seeds = [KEY_SEED, identity_account.key().as_ref(), &hash_key(&key)],
+        bump = key_account.bump
+    )]
+    pub key_account: Account<'info, KeyAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(purpose: u32)]
+pub struct KeysByPurpose<'info> {
+    pub identity_account: Account<'info, IdentityAccount>,
+    #[account(
+        seeds = [KEYS_SEED, identity_account.key().as_ref(), &purpose.to_le_bytes()],
+        bump = purpose_keys.bump
+    )]
+    pub purpose_keys: Account<'info, PurposeKeysAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(topic: u64, scheme: u64, issuer_wallet: Pubkey, issuer: Pubkey)]
+pub struct ClaimContext<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = Claim::LEN,
+        seeds = [CLAIM_SEED, key_context.identity_account.key().as_ref(), &hash_claim(&issuer, topic)],
+        bump
+    )]
+    pub claim: Account<'info, Claim>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = ClaimTopicIndex::LEN,
+        seeds = [CLAIMS_SEED, key_context.identity_account.key().as_ref(), &topic.to_le_bytes()],
+        bump
+    )]
+    pub claim_index: Account<'info, ClaimTopicIndex>,
     pub key_context: _Key<'info>,
     /// CHECK: This is safe because we are only reading the instructions sysvar.
     #[account(address = solana_program::sysvar::instructions::id())]
     pub instructions: AccountInfo<'info>,
+    /// Set when the claim issuer is a `claim_issuer` that validates its own claims
+    pub claim_issuer_program: Option<Program<'info, ClaimIssuer>>,
+    /// CHECK: The claim issuer's keys account, checked by the claim issuer program.
+    pub issuer_keys: Option<UncheckedAccount<'info>>,
+    /// CHECK: The claim issuer's `[REVOKED_CLAIM_SEED, issuer_keys, hash(signature)]` PDA, checked by the claim issuer program.
+    pub issuer_revoked_claim: Option<UncheckedAccount<'info>>,
+    /// CHECK: The identity's key that signed a claim of another issuer, checked by `is_claim_valid`.
+    pub claim_key: Option<UncheckedAccount<'info>>,
+    /// Set when the claim issuer is an EVM claim issuer recorded by the identity's factory.
+    #[account(
+        seeds = [EVM_ISSUER_SEED, key_context.identity_account.factory.as_ref(), issuer.as_ref()],
+        bump = evm_issuer.bump
+    )]
+    pub evm_issuer: Option<Account<'info, EvmIssuer>>,
+    /// Pays the rent of the claim, and of its topic index when it is the first.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(topic: u64, issuer: Pubkey)]
+pub struct RemoveClaim<'info> {
+    #[account(
+        mut,
+        close = claim_payer,
+        seeds = [CLAIM_SEED, key_context.identity_account.key().as_ref(), &hash_claim(&issuer, topic)],
+        bump = claim.bump
+    )]
+    pub claim: Account<'info, Claim>,
+    #[account(
+        mut,
+        seeds = [CLAIMS_SEED, key_context.identity_account.key().as_ref(), &topic.to_le_bytes()],
+        bump = claim_index.bump
+    )]
+    pub claim_index: Account<'info, ClaimTopicIndex>,
+    pub key_context: _Key<'info>,
+    /// CHECK: Paid the claim's rent, and receives it back.
+    #[account(mut, address = claim.payer @ Error::InvalidClaimPayer)]
+    pub claim_payer: UncheckedAccount<'info>,
+    /// CHECK: Paid the topic index's rent, and receives it back once the index is empty.
+    #[account(mut, address = claim_index.payer @ Error::InvalidClaimPayer)]
+    pub index_payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(claim_id: [u8; 32])]
+pub struct GetClaim<'info> {
+    pub identity_account: Account<'info, IdentityAccount>,
+    #[account(
+        seeds = [CLAIM_SEED, identity_account.key().as_ref(), &claim_id],
+        bump = claim.bump
+    )]
+    pub claim: Account<'info, Claim>,
+}
+
+#[derive(Accounts)]
+#[instruction(topic: u64)]
+pub struct GetClaimIds<'info> {
+    pub identity_account: Account<'info, IdentityAccount>,
+    #[account(
+        seeds = [CLAIMS_SEED, identity_account.key().as_ref(), &topic.to_le_bytes()],
+        bump = claim_index.bump
+    )]
+    pub claim_index: Account<'info, ClaimTopicIndex>,
 }
 
 /// Context accounts
@@ -403,6 +1673,79 @@ pub struct Verify<'info> {
 pub struct IdentityAccount {
     pub initialized: bool,
     pub factory: Pubkey,
+    pub wallet: Pubkey,
+    pub salt: [u8; 32],
+    pub bump: u8,
+    pub execution_nonce: u64,
+    pub management_threshold: u8,
+    pub action_threshold: u8,
+    /// Number of times a key lost a purpose, see `record_approval`.
+    pub key_removals: u64,
+    /// Whether the factory may manage the identity, see `identity_require_auth`.
+    pub factory_is_manager: bool,
+}
+
+impl IdentityAccount {
+    const LEN: usize = 8 + 1 + 32 + 32 + 32 + 1 + 8 + 1 + 1 + 8 + 1;
+
+    /// Number of distinct keys that must approve operations for `purpose`.
+    fn threshold(&self, purpose: KeyPurpose) -> u8 {
+        match purpose {
+            KeyPurpose::Management => self.management_threshold,
+            KeyPurpose::Action => self.action_threshold,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct ExecutionAccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl ExecutionAccountMeta {
+    const LEN: usize = 32 + 1 + 1;
+}
+
+/// A pending or executed ERC-734 execution, stored at
+/// `[EXECUTION_SEED, identity, execution_id]`.
+#[account]
+pub struct Execution {
+    pub identity: Pubkey,
+    pub id: u64,
+    pub to: Pubkey,
+    pub accounts: Vec<ExecutionAccountMeta>,
+    pub data: Vec<u8>,
+    /// Hashes of the keys that approved the execution.
+    pub approvals: Vec<[u8; 32]>,
+    /// The identity's `key_removals` when the approvals were collected.
+    pub key_removals: u64,
+    /// Incremented whenever approvals are withdrawn or dropped, so that the
+    /// signatures of Ethereum-address keys cannot be replayed afterwards.
+    pub approval_nonce: u64,
+    pub executed: bool,
+    pub bump: u8,
+}
+
+impl Execution {
+    const MAX_APPROVALS: usize = PurposeKeysAccount::MAX_KEYS;
+
+    fn space(accounts_len: usize, data_len: usize) -> usize {
+        8 + 32 + 8 + 32 + 4 + accounts_len * ExecutionAccountMeta::LEN + 4 + data_len
+            + 4 + Self::MAX_APPROVALS * 32 + 8 + 8 + 1 + 1
+    }
+
+    /// Executions against the identity program itself need a Management key,
+    /// anything else an Action key.
+    fn required_purpose(&self) -> KeyPurpose {
+        if self.to == crate::ID {
+            KeyPurpose::Management
+        } else {
+            KeyPurpose::Action
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
@@ -427,21 +1770,61 @@ impl TryFrom<u32> for KeyPurpose {
     }
 }
 
+impl KeyPurpose {
+    /// Bit of the purpose in `KeyAccount::purposes`.
+    pub fn mask(self) -> u32 {
+        1 << (self as u32 - 1)
+    }
+}
+
+/// A management key of a new identity, see `initialize_with_management_keys`.
+#[derive(Clone, Copy, AnchorSerialize, AnchorDeserialize)]
+pub enum ManagementKey {
+    Ed25519(Pubkey),
+    /// An Ethereum address, as used by `add_eth_key`.
+    Ethereum([u8; 20]),
+}
+
+impl ManagementKey {
+    pub fn hash(&self) -> [u8; 32] {
+        match self {
+            ManagementKey::Ed25519(key) => hash_key(key),
+            ManagementKey::Ethereum(address) => hash_eth_address(address),
+        }
+    }
+
+    pub fn key_type(&self) -> KeyType {
+        match self {
+            ManagementKey::Ed25519(_) => KeyType::Ed25519,
+            ManagementKey::Ethereum(_) => KeyType::ECDSA,
+        }
+    }
+}
+
 #[derive(Clone, Copy, AnchorSerialize, AnchorDeserialize)]
 pub enum KeyType {
     ECDSA = 1,
     RSA = 2,
+    Ed25519 = 3,
 }
 
+/// A key of an identity, stored at `[KEY_SEED, identity, key_hash]`.
 #[account]
-pub struct NewKey {
-    pub purposes: Vec<KeyPurpose>,
-    pub key_type: KeyType,
+pub struct KeyAccount {
+    pub bump: u8,
+    pub identity: Pubkey,
     pub key: [u8; 32],
+    /// Bitmask of the key's purposes, see `KeyPurpose::mask`.
+    pub purposes: u32,
+    pub key_type: KeyType,
 }
 
-impl NewKey {
-    const LEN: usize = 4 + (1 * 4) + 4 + 32;
+impl KeyAccount {
+    const LEN: usize = 8 + 1 + 32 + 32 + 4 + 1;
+
+    pub fn has_purpose(&self, purpose: KeyPurpose) -> bool {
+        self.purposes & purpose.mask() != 0
+    }
 }
 
 impl TryFrom<u32> for KeyType {
@@ -451,34 +1834,101 @@ impl TryFrom<u32> for KeyType {
         match value {
             1 => Ok(KeyType::ECDSA),
             2 => Ok(KeyType::RSA),
+            3 => Ok(KeyType::Ed25519),
             _ => Err(Error::InvalidKeyType),
         }
     }
 }
 
+/// Hashes of an identity's keys holding a purpose, stored at
+/// `[KEYS_SEED, identity, purpose]`.
 #[account]
-pub struct KeysAccount {
-    pub keys: Vec<NewKey>,
+pub struct PurposeKeysAccount {
+    pub bump: u8,
+    pub purpose: u32,
+    pub keys: Vec<[u8; 32]>,
 }
 
+impl PurposeKeysAccount {
+    pub const MAX_KEYS: usize = 16;
+    const LEN: usize = 8 + 1 + 4 + 4 + Self::MAX_KEYS * 32;
+}
+
+/// Ids of an identity's claims on a topic, stored at `[CLAIMS_SEED, identity, topic]`.
 #[account]
-pub struct ClaimsAccount {
-    pub claims: Vec<([u8; 32], Claim)>, 
+pub struct ClaimTopicIndex {
+    pub bump: u8,
+    pub topic: u64,
+    /// Paid the index's rent, refunded when its last claim is removed.
+    pub payer: Pubkey,
+    pub claim_ids: Vec<[u8; 32]>,
 }
 
+impl ClaimTopicIndex {
+    const MAX_CLAIMS: usize = 16;
+    const LEN: usize = 8 + 1 + 8 + 32 + 4 + Self::MAX_CLAIMS * 32;
+}
+
+/// The Claim keys of an EVM claim issuer, recorded by a factory for its
+/// identities at `[EVM_ISSUER_SEED, factory, issuer]`, `issuer` being the
+/// issuer's address as a `Pubkey` (see `eth_address_pubkey`).
+#[account]
+pub struct EvmIssuer {
+    pub bump: u8,
+    pub issuer: Pubkey,
+    /// Ethereum addresses of the issuer's Claim keys.
+    pub claim_keys: Vec<[u8; 20]>,
+}
+
+impl EvmIssuer {
+    pub const MAX_CLAIM_KEYS: usize = 16;
+    const LEN: usize = 8 + 1 + 32 + 4 + Self::MAX_CLAIM_KEYS * 20;
+}
+
+/// A claim about an identity, stored at `[CLAIM_SEED, identity, claim_id]`.
 #[account]
 pub struct Claim {
-    topic: u64,          
-    scheme: u64,
-    issuer_wallet: Pubkey,
-    issuer: Pubkey,
-    signature: [u8; 64], 
-    data: Vec<u8>,      
-    uri: String,
+    pub bump: u8,
+    pub topic: u64,          
+    pub scheme: u64,
+    pub issuer_wallet: Pubkey,
+    pub issuer: Pubkey,
+    pub signature: Vec<u8>,
+    pub data: Vec<u8>,      
+    pub uri: String,
+    pub valid_from: i64,
+    pub valid_until: i64,
+    /// Paid the claim's rent, which is refunded to it when the claim is removed.
+    pub payer: Pubkey,
 }
 
 impl Claim {
-    const LEN: usize = 8 + 8 + 32 + 32 + 64 + 2048 + 512;
+    const LEN: usize = 8 + 1 + 8 + 8 + 32 + 32 + (4 + 65) + (4 + 2048) + (4 + 512) + 8 + 8 + 32;
+
+    /// Whether the claim is within its validity window; expired claims are
+    /// invalid without being revoked or removed.
+    pub fn is_active(&self) -> Result<bool> {
+        is_within_validity(self.valid_from, self.valid_until)
+    }
+}
+
+/// Signature schemes of claims, numbered after ERC-735 where it defines one.
+#[derive(Clone, Copy, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum ClaimScheme {
+    Ecdsa = 1,
+    Ed25519 = 4,
+}
+
+impl TryFrom<u64> for ClaimScheme {
+    type Error = Error;
+
+    fn try_from(value: u64) -> std::result::Result<ClaimScheme, Error> {
+        match value {
+            1 => Ok(ClaimScheme::Ecdsa),
+            4 => Ok(ClaimScheme::Ed25519),
+            _ => Err(Error::InvalidClaimScheme),
+        }
+    }
 }
 
 #[error_code]
@@ -511,4 +1961,26 @@ pub enum Error {
     InvalidIssuer,
     #[msg("The address bytes are invalid.")]
     InvalidAddressBytes,
+    #[msg("The execution has already been executed.")]
+    ExecutionAlreadyExecuted,
+    #[msg("The key has already approved this execution.")]
+    AlreadyApproved,
+    #[msg("The operation needs more approvals; submit it as an execution.")]
+    ApprovalThresholdNotMet,
+    #[msg("The threshold must be between one and the number of keys with the purpose.")]
+    InvalidThreshold,
+    #[msg("The claim signature scheme is not supported.")]
+    InvalidClaimScheme,
+    #[msg("The claim is expired or not yet valid.")]
+    ClaimExpired,
+    #[msg("The topic has reached its maximum number of claims.")]
+    TooManyClaims,
+    #[msg("The purpose has reached its maximum number of keys.")]
+    TooManyKeys,
+    #[msg("An identity needs at least one management key.")]
+    NoManagementKeys,
+    #[msg("The key account is not the key's PDA.")]
+    InvalidKeyAccount,
+    #[msg("The account is not the payer of the claim.")]
+    InvalidClaimPayer,
 }
\ No newline at end of file
@@ -0,0 +1,153 @@
+use anchor_lang::prelude::*;
+use solana_program::instruction::Instruction;
+use solana_program::keccak;
+use solana_program::secp256k1_program::ID as SECP256K1_ID;
+use solana_program::secp256k1_recover::secp256k1_recover;
+use solana_program::sysvar::instructions::load_instruction_at_checked;
+use crate::error::ErrorCode;
+use super::ed25519::num_instructions;
+
+use std::convert::TryInto;
+
+/// Verify that `sig` by `eth_address` over `msg` was checked by a
+/// Secp256k1Program instruction of the current transaction.
+///
+/// With an `ix_index` hint only that instruction is looked at, otherwise every
+/// instruction of the transaction is scanned.
+pub fn verify_secp256k1_in_tx(
+    instructions: &AccountInfo,
+    ix_index: Option<u8>,
+    eth_address: &[u8],
+    msg: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    if let Some(index) = ix_index {
+        let ix: Instruction = load_instruction_at_checked(index as usize, instructions)?;
+        return verify_secp256k1_ix(&ix, index, eth_address, msg, sig);
+    }
+
+    for index in 0..num_instructions(instructions)? {
+        let ix: Instruction = load_instruction_at_checked(index, instructions)?;
+        if ix.program_id == SECP256K1_ID && verify_secp256k1_ix(&ix, index as u8, eth_address, msg, sig).is_ok() {
+            return Ok(());
+        }
+    }
+
+    Err(ErrorCode::SigVerificationFailed.into())
+}
+
+/// Verify Secp256k1Program instruction fields
+pub fn verify_secp256k1_ix(ix: &Instruction, ix_index: u8, eth_address: &[u8], msg: &[u8], sig: &[u8]) -> Result<()> {
+    if  ix.program_id       != SECP256K1_ID                 ||  // The program id we expect
+        !ix.accounts.is_empty()                             ||  // With no context accounts
+        ix.data.len()       != (12 + 20 + 65 + msg.len())       // And data of this size
+    {
+        return Err(ErrorCode::SigVerificationFailed.into());    // Otherwise, we can already throw err
+    }
+
+    check_secp256k1_data(&ix.data, ix_index, eth_address, msg, sig)?; // If that's not the case, check data
+
+    Ok(())
+}
+
+/// Verify serialized Secp256k1Program instruction data
+pub fn check_secp256k1_data(data: &[u8], ix_index: u8, eth_address: &[u8], msg: &[u8], sig: &[u8]) -> Result<()> {
+    // According to this layout used by the Secp256k1Program
+    // https://github.com/solana-labs/solana-web3.js/blob/master/src/programs/secp256k1.ts
+
+    // "Deserializing" byte slices
+
+    let num_signatures                  = data[0];           // Byte  0
+    let signature_offset                = &data[1..=2];      // Bytes 1,2
+    let signature_instruction_index     = data[3];           // Byte  3
+    let eth_address_offset              = &data[4..=5];      // Bytes 4,5
+    let eth_address_instruction_index   = data[6];           // Byte  6
+    let message_data_offset             = &data[7..=8];      // Bytes 7,8
+    let message_data_size               = &data[9..=10];     // Bytes 9,10
+    let message_instruction_index       = data[11];          // Byte  11
+
+    let data_eth_address                = &data[12..12+20];  // Bytes 12..12+20
+    let data_sig                        = &data[32..32+65];  // Bytes 32..32+65, signature and recovery id
+    let data_msg                        = &data[97..];       // Bytes 97..end
+
+    // Expected values
+
+    let exp_eth_address_offset:     u16 = 12; // u8 + 4*u16 + 3*u8
+    let exp_signature_offset:       u16 = exp_eth_address_offset + eth_address.len() as u16;
+    let exp_message_data_offset:    u16 = exp_signature_offset + sig.len() as u16;
+    let exp_num_signatures:          u8 = 1;
+    let exp_message_data_size:      u16 = msg.len().try_into().unwrap();
+
+    // Header and Arg Checks
+
+    // Header, with every offset pointing into the Secp256k1Program instruction itself
+    if  num_signatures                  != exp_num_signatures                       ||
+        signature_offset                != exp_signature_offset.to_le_bytes()       ||
+        signature_instruction_index     != ix_index                                 ||
+        eth_address_offset              != exp_eth_address_offset.to_le_bytes()     ||
+        eth_address_instruction_index   != ix_index                                 ||
+        message_data_offset             != exp_message_data_offset.to_le_bytes()    ||
+        message_data_size               != exp_message_data_size.to_le_bytes()      ||
+        message_instruction_index       != ix_index
+    {
+        return Err(ErrorCode::SigVerificationFailed.into());
+    }
+
+    // Arguments
+    if  data_eth_address    != eth_address  ||
+        data_msg            != msg          ||
+        data_sig            != sig
+    {
+        return Err(ErrorCode::SigVerificationFailed.into());
+    }
+
+    Ok(())
+}
+
+/// Wraps `msg` the way Ethereum wallets do for `personal_sign` (EIP-191),
+/// so that approvals can be signed with an ordinary EVM wallet.
+pub fn eth_signed_message(msg: &[u8]) -> Vec<u8> {
+    let mut prefixed = format!("\x19Ethereum Signed Message:\n{}", msg.len()).into_bytes();
+    prefixed.extend_from_slice(msg);
+    prefixed
+}
+
+/// The `r || s || recovery id` form the Secp256k1Program verifies of a 65-byte
+/// EVM `r || s || v` signature.
+pub fn secp256k1_precompile_signature(sig: &[u8]) -> Result<[u8; 65]> {
+    let sig: [u8; 65] = sig.try_into().map_err(|_| ErrorCode::SigVerificationFailed)?;
+
+    // Reject malleable signatures, like OpenZeppelin's ECDSA does
+    if sig[32..64] > SECP256K1_HALF_ORDER[..] {
+        return Err(ErrorCode::SigVerificationFailed.into());
+    }
+
+    // Accept both the legacy (27/28) and the raw (0/1) recovery ids
+    let mut precompile_sig = sig;
+    precompile_sig[64] = match sig[64] {
+        0 | 27 => 0,
+        1 | 28 => 1,
+        _ => return Err(ErrorCode::SigVerificationFailed.into()),
+    };
+
+    Ok(precompile_sig)
+}
+
+/// The Ethereum address that signed `hash` with the 65-byte EVM `r || s || v`
+/// signature `sig`, recovered through the `secp256k1_recover` syscall, which
+/// unlike the Secp256k1Program needs no instruction in the transaction.
+pub fn recover_eth_address(hash: &[u8; 32], sig: &[u8]) -> Result<[u8; 20]> {
+    let sig = secp256k1_precompile_signature(sig)?;
+    let pubkey = secp256k1_recover(hash, sig[64], &sig[..64]).map_err(|_| ErrorCode::SigVerificationFailed)?;
+
+    let mut eth_address = [0u8; 20];
+    eth_address.copy_from_slice(&keccak::hash(&pubkey.to_bytes()).to_bytes()[12..]);
+
+    Ok(eth_address)
+}
+
+/// Half of the secp256k1 curve order, the upper bound of a non-malleable `s`.
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
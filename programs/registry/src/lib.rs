@@ -0,0 +1,392 @@
+use anchor_lang::prelude::*;
+use claim_issuer_lib::{is_vouched_for, program::ClaimIssuer, KeysAccount};
+use factory_lib::linked_identity;
+use identity_lib::{hash_claim, Claim, CLAIM_SEED};
+
+declare_id!("4rdFzVoXpjgfJwPETN8n9LAqKpHNibzMzTeuD15LTs4F");
+
+pub const REGISTRY_SEED: &[u8] = b"registry";
+pub const TRUSTED_ISSUERS_SEED: &[u8] = b"trusted_issuers";
+
+/// Accounts `verify` expects in `remaining_accounts` for each required topic.
+pub const ACCOUNTS_PER_TOPIC: usize = 4;
+
+#[program]
+pub mod registry {
+    use super::*;
+
+    /// Creates the registry of `owner`, verifying the wallets `factory` links
+    /// to identities.
+    pub fn initialize(ctx: Context<Initialize>, factory: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        registry.bump = ctx.bumps.registry;
+        registry.owner = ctx.accounts.owner.key();
+        registry.factory = factory;
+
+        Ok(())
+    }
+
+    /// Requires a claim on `topic` from every verified identity.
+    pub fn add_claim_topic(ctx: Context<UpdateRegistry>, topic: u64) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+
+        if registry.claim_topics.contains(&topic) {
+            return Err(ErrorCode::TopicAlreadyRequired.into());
+        }
+        if registry.claim_topics.len() >= Registry::MAX_CLAIM_TOPICS {
+            return Err(ErrorCode::TooManyClaimTopics.into());
+        }
+        registry.claim_topics.push(topic);
+
+        emit!(ClaimTopicAdded { topic });
+
+        Ok(())
+    }
+
+    pub fn remove_claim_topic(ctx: Context<UpdateRegistry>, topic: u64) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+
+        let index = registry
+            .claim_topics
+            .iter()
+            .position(|t| *t == topic)
+            .ok_or(ErrorCode::TopicNotRequired)?;
+        registry.claim_topics.remove(index);
+
+        emit!(ClaimTopicRemoved { topic });
+
+        Ok(())
+    }
+
+    pub fn get_claim_topics(ctx: Context<GetRegistry>) -> Result<Vec<u64>> {
+        Ok(ctx.accounts.registry.claim_topics.clone())
+    }
+
+    /// Trusts `issuer` for claims on `topic`. A claim issuer is identified by
+    /// its keys account, which is the issuer recorded in the claims it signs.
+    pub fn add_trusted_issuer(ctx: Context<AddTrustedIssuer>, topic: u64, issuer: Pubkey) -> Result<()> {
+        let trusted_issuers = &mut ctx.accounts.trusted_issuers;
+        trusted_issuers.bump = ctx.bumps.trusted_issuers;
+        trusted_issuers.topic = topic;
+
+        if trusted_issuers.issuers.contains(&issuer) {
+            return Err(ErrorCode::IssuerAlreadyTrusted.into());
+        }
+        if trusted_issuers.issuers.len() >= TrustedIssuers::MAX_ISSUERS {
+            return Err(ErrorCode::TooManyTrustedIssuers.into());
+        }
+        trusted_issuers.issuers.push(issuer);
+
+        emit!(TrustedIssuerAdded { topic, issuer });
+
+        Ok(())
+    }
+
+    pub fn remove_trusted_issuer(ctx: Context<RemoveTrustedIssuer>, topic: u64, issuer: Pubkey) -> Result<()> {
+        let trusted_issuers = &mut ctx.accounts.trusted_issuers;
+
+        let index = trusted_issuers
+            .issuers
+            .iter()
+            .position(|i| *i == issuer)
+            .ok_or(ErrorCode::IssuerNotTrusted)?;
+        trusted_issuers.issuers.remove(index);
+
+        emit!(TrustedIssuerRemoved { topic, issuer });
+
+        Ok(())
+    }
+
+    pub fn get_trusted_issuers(ctx: Context<GetTrustedIssuers>, _topic: u64) -> Result<Vec<Pubkey>> {
+        Ok(ctx.accounts.trusted_issuers.issuers.clone())
+    }
+
+    /// Whether the identity linked to `wallet` holds an active, unrevoked claim
+    /// from a trusted issuer for every required topic.
+    ///
+    /// For each required topic, in order, `remaining_accounts` must hold the
+    /// topic's trusted issuers, the identity's claim from one of them, and
+    /// that issuer's keys account and the claim's `RevokedClaim` PDA. Each claim is checked
+    /// again by its issuer, so the transaction must also verify the claim's
+    /// signature through an Ed25519Program instruction.
+    pub fn verify<'info>(ctx: Context<'_, '_, 'info, 'info, Verify<'info>>, wallet: Pubkey) -> Result<bool> {
+        let identity = linked_identity(&ctx.accounts.wallet_account, &ctx.accounts.registry.factory, &wallet)?;
+
+        let registry = &ctx.accounts.registry;
+        if ctx.remaining_accounts.len() != registry.claim_topics.len() * ACCOUNTS_PER_TOPIC {
+            return Err(ErrorCode::InvalidVerificationAccounts.into());
+        }
+
+        for (topic, accounts) in registry
+            .claim_topics
+            .iter()
+            .zip(ctx.remaining_accounts.chunks(ACCOUNTS_PER_TOPIC))
+        {
+            if !has_trusted_claim(&ctx.accounts.claim_issuer_program, &ctx.accounts.instructions, &registry.key(), &identity, *topic, accounts)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Whether `accounts` hold a claim on `topic` for `identity` from an issuer
+/// trusted for the topic, which the issuer's `is_claim_valid` still vouches
+/// for: signed by one of its Claim keys, not revoked, and within its window.
+///
+/// Only an untrusted issuer, an expired or revoked claim, or a claim key that
+/// lost its Claim purpose makes this false. Other accounts than the expected
+/// ones, a missing claim or an unverified signature are errors, so that the
+/// caller's choice of accounts can't make a valid claim look invalid.
+fn has_trusted_claim<'info>(
+    claim_issuer_program: &Program<'info, ClaimIssuer>,
+    instructions: &AccountInfo<'info>,
+    registry: &Pubkey,
+    identity: &Pubkey,
+    topic: u64,
+    accounts: &'info [AccountInfo<'info>],
+) -> Result<bool> {
+    let [trusted_issuers_info, claim_info, issuer_keys_info, revoked_claim_info] = accounts else {
+        return Err(ErrorCode::InvalidVerificationAccounts.into());
+    };
+
+    let trusted_issuers_address =
+        Pubkey::find_program_address(&[TRUSTED_ISSUERS_SEED, registry.as_ref(), &topic.to_le_bytes()], &crate::ID).0;
+    if trusted_issuers_info.key() != trusted_issuers_address {
+        return Err(ErrorCode::InvalidVerificationAccounts.into());
+    }
+    if claim_info.owner != &identity_lib::ID {
+        return Err(ErrorCode::ClaimNotFound.into());
+    }
+
+    // A topic nobody has been trusted for fails verification
+    if trusted_issuers_info.owner != &crate::ID {
+        return Ok(false);
+    }
+    let trusted_issuers = Account::<TrustedIssuers>::try_from(trusted_issuers_info)?;
+
+    // The claim must be the identity's claim on this topic
+    let claim = Account::<Claim>::try_from(claim_info)?;
+    let claim_address = Pubkey::create_program_address(
+        &[CLAIM_SEED, identity.as_ref(), &hash_claim(&claim.issuer, topic), &[claim.bump]],
+        &identity_lib::ID,
+    )
+    .map_err(|_| ErrorCode::InvalidVerificationAccounts)?;
+    if claim_info.key() != claim_address {
+        return Err(ErrorCode::InvalidVerificationAccounts.into());
+    }
+
+    if !trusted_issuers.issuers.contains(&claim.issuer) || !claim.is_active()? {
+        return Ok(false);
+    }
+
+    // The issuer's keys must be the claim's issuer; the issuer checks the rest
+    if issuer_keys_info.key() != claim.issuer {
+        return Err(ErrorCode::InvalidVerificationAccounts.into());
+    }
+
+    // Claim issuers only sign Ed25519 claims
+    let signature = <[u8; 64]>::try_from(&claim.signature[..]).map_err(|_| ErrorCode::InvalidClaimSignature)?;
+
+    let cpi_ctx = CpiContext::new(
+        claim_issuer_program.to_account_info(),
+        claim_issuer_lib::cpi::accounts::IsClaimValid {
+            keys: issuer_keys_info.clone(),
+            revoked_claim: revoked_claim_info.clone(),
+            instructions: instructions.clone(),
+        },
+    );
+    let is_valid = claim_issuer_lib::cpi::is_claim_valid(
+        cpi_ctx,
+        *identity,
+        topic,
+        claim.issuer_wallet,
+        signature,
+        claim.data.clone(),
+        claim.valid_from,
+        claim.valid_until,
+        None,
+    )?
+    .get();
+    if is_valid {
+        return Ok(true);
+    }
+
+    // A claim the issuer still vouches for was only rejected for its signature
+    let issuer_keys = Account::<KeysAccount>::try_from(issuer_keys_info)?;
+    require!(
+        !is_vouched_for(&issuer_keys, revoked_claim_info, &claim.issuer_wallet),
+        ErrorCode::InvalidClaimSignature
+    );
+
+    Ok(false)
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = Registry::LEN,
+        seeds = [REGISTRY_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub registry: Account<'info, Registry>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRegistry<'info> {
+    #[account(
+        mut,
+        seeds = [REGISTRY_SEED, owner.key().as_ref()],
+        bump = registry.bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetRegistry<'info> {
+    pub registry: Account<'info, Registry>,
+}
+
+#[derive(Accounts)]
+#[instruction(topic: u64)]
+pub struct AddTrustedIssuer<'info> {
+    #[account(
+        seeds = [REGISTRY_SEED, owner.key().as_ref()],
+        bump = registry.bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = TrustedIssuers::LEN,
+        seeds = [TRUSTED_ISSUERS_SEED, registry.key().as_ref(), &topic.to_le_bytes()],
+        bump
+    )]
+    pub trusted_issuers: Account<'info, TrustedIssuers>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(topic: u64)]
+pub struct RemoveTrustedIssuer<'info> {
+    #[account(
+        seeds = [REGISTRY_SEED, owner.key().as_ref()],
+        bump = registry.bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+    #[account(
+        mut,
+        seeds = [TRUSTED_ISSUERS_SEED, registry.key().as_ref(), &topic.to_le_bytes()],
+        bump = trusted_issuers.bump
+    )]
+    pub trusted_issuers: Account<'info, TrustedIssuers>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(topic: u64)]
+pub struct GetTrustedIssuers<'info> {
+    pub registry: Account<'info, Registry>,
+    #[account(
+        seeds = [TRUSTED_ISSUERS_SEED, registry.key().as_ref(), &topic.to_le_bytes()],
+        bump = trusted_issuers.bump
+    )]
+    pub trusted_issuers: Account<'info, TrustedIssuers>,
+}
+
+#[derive(Accounts)]
+pub struct Verify<'info> {
+    pub registry: Account<'info, Registry>,
+    /// CHECK: The registry factory's `[WALLET_SEED, factory, wallet]` PDA, see `linked_identity`.
+    pub wallet_account: UncheckedAccount<'info>,
+    pub claim_issuer_program: Program<'info, ClaimIssuer>,
+    /// CHECK: The instructions sysvar, read by the claim issuers' `is_claim_valid`.
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions: AccountInfo<'info>,
+}
+
+/// Claim topics an identity must hold, like ERC-3643's ClaimTopicsRegistry.
+#[account]
+pub struct Registry {
+    pub bump: u8,
+    pub owner: Pubkey,
+    /// The factory whose wallet links identify the verified wallets.
+    pub factory: Pubkey,
+    pub claim_topics: Vec<u64>,
+}
+
+impl Registry {
+    pub const MAX_CLAIM_TOPICS: usize = 15;
+    const LEN: usize = 8 + 1 + 32 + 32 + 4 + Self::MAX_CLAIM_TOPICS * 8;
+}
+
+/// Claim issuers trusted for one topic, like ERC-3643's TrustedIssuersRegistry.
+#[account]
+pub struct TrustedIssuers {
+    pub bump: u8,
+    pub topic: u64,
+    pub issuers: Vec<Pubkey>,
+}
+
+impl TrustedIssuers {
+    pub const MAX_ISSUERS: usize = 50;
+    const LEN: usize = 8 + 1 + 8 + 4 + Self::MAX_ISSUERS * 32;
+}
+
+#[event]
+pub struct ClaimTopicAdded {
+    pub topic: u64,
+}
+
+#[event]
+pub struct ClaimTopicRemoved {
+    pub topic: u64,
+}
+
+#[event]
+pub struct TrustedIssuerAdded {
+    pub topic: u64,
+    pub issuer: Pubkey,
+}
+
+#[event]
+pub struct TrustedIssuerRemoved {
+    pub topic: u64,
+    pub issuer: Pubkey,
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Only the registry owner can do this")]
+    Unauthorized,
+    #[msg("The claim topic is already required")]
+    TopicAlreadyRequired,
+    #[msg("The claim topic is not required")]
+    TopicNotRequired,
+    #[msg("Too many claim topics")]
+    TooManyClaimTopics,
+    #[msg("The issuer is already trusted for this topic")]
+    IssuerAlreadyTrusted,
+    #[msg("The issuer is not trusted for this topic")]
+    IssuerNotTrusted,
+    #[msg("Too many trusted issuers for this topic")]
+    TooManyTrustedIssuers,
+    #[msg("Invalid accounts for verification")]
+    InvalidVerificationAccounts,
+    #[msg("The identity has no claim at the given account")]
+    ClaimNotFound,
+    #[msg("The claim's signature is not verified by the transaction")]
+    InvalidClaimSignature,
+}
@@ -0,0 +1,349 @@
+use anchor_lang::{prelude::*, solana_program::program_option::COption, system_program};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use claim_issuer_lib::program::ClaimIssuer;
+use factory_lib::{linked_identity, WALLET_SEED};
+use identity_lib::Claim;
+use registry_lib::{program::Registry as RegistryProgram, Registry, ACCOUNTS_PER_TOPIC};
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed, state::ExtraAccountMetaList};
+use spl_transfer_hook_interface::instruction::{ExecuteInstruction, TransferHookInstruction};
+
+declare_id!("Fu47HxQURdZBiMcCZ2i2kiq3Q2khbLTyoLHuoM8L438M");
+
+/// Seed of the account Token-2022 reads a mint's extra transfer accounts from.
+pub const EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"extra-account-metas";
+pub const VERIFICATION_SEED: &[u8] = b"verification";
+
+/// Seconds a verification lets a wallet transfer before it must be refreshed
+/// with `verify_wallet`. Transfers only read the recorded verification, so a
+/// claim that is revoked, or whose key loses its Claim purpose, keeps
+/// allowing transfers for at most this long unless someone refreshes the wallet.
+pub const MAX_VERIFICATION_AGE: i64 = 24 * 60 * 60;
+
+/// Indexes of the registry and the factory program among the accounts of a
+/// transfer hook execution.
+const REGISTRY_ACCOUNT_INDEX: u8 = 5;
+const FACTORY_PROGRAM_ACCOUNT_INDEX: u8 = 6;
+
+#[program]
+pub mod transfer_hook {
+    use super::*;
+
+    /// Makes transfers of `mint` require verified sender and receiver wallets
+    /// in `registry`. Only the mint authority can do this.
+    pub fn initialize_extra_account_meta_list(ctx: Context<InitializeExtraAccountMetaList>) -> Result<()> {
+        let account_metas = extra_account_metas(&ctx.accounts.registry)?;
+
+        let extra_account_meta_list = &ctx.accounts.extra_account_meta_list;
+        let size = ExtraAccountMetaList::size_of(account_metas.len())?;
+        let lamports = Rent::get()?.minimum_balance(size);
+        let mint = ctx.accounts.mint.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            EXTRA_ACCOUNT_METAS_SEED,
+            mint.as_ref(),
+            &[ctx.bumps.extra_account_meta_list],
+        ]];
+
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::CreateAccount {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: extra_account_meta_list.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            lamports,
+            size as u64,
+            ctx.program_id,
+        )?;
+
+        ExtraAccountMetaList::init::<ExecuteInstruction>(
+            &mut extra_account_meta_list.try_borrow_mut_data()?,
+            &account_metas,
+        )?;
+
+        Ok(())
+    }
+
+    /// Records whether `wallet` is verified by the registry, so transfers can
+    /// check it. Anyone can refresh a wallet, which is how revoked or changed
+    /// claims take effect; a verification older than `MAX_VERIFICATION_AGE`
+    /// no longer lets the wallet transfer.
+    ///
+    /// A negative verdict is only recorded when it was reached from the claims
+    /// the wallet's verification relies on, so that other claims, which the
+    /// caller chose, can't revoke a verification they didn't grant.
+    ///
+    /// `remaining_accounts` are those of the registry's `verify`, which needs
+    /// the claims' Ed25519Program instructions in the transaction.
+    pub fn verify_wallet<'info>(
+        ctx: Context<'_, '_, 'info, 'info, VerifyWallet<'info>>,
+        wallet: Pubkey,
+    ) -> Result<bool> {
+        let identity = linked_identity(&ctx.accounts.wallet_account, &ctx.accounts.registry.factory, &wallet)?;
+
+        let claim_infos = || ctx.remaining_accounts.iter().skip(1).step_by(ACCOUNTS_PER_TOPIC);
+        let claims: Vec<Pubkey> = claim_infos().map(|claim_info| claim_info.key()).collect();
+        let verification = &ctx.accounts.verification;
+        let relies_on_claims =
+            verification.is_verified && verification.identity == identity && verification.claims == claims;
+
+        // A removed claim can't be verified, but revokes the verifications relying on it
+        let is_claim_removed = relies_on_claims && claim_infos().any(|claim_info| claim_info.owner != &identity_lib::ID);
+        let is_verified = !is_claim_removed && {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.registry_program.to_account_info(),
+                registry_lib::cpi::accounts::Verify {
+                    registry: ctx.accounts.registry.to_account_info(),
+                    wallet_account: ctx.accounts.wallet_account.to_account_info(),
+                    claim_issuer_program: ctx.accounts.claim_issuer_program.to_account_info(),
+                    instructions: ctx.accounts.instructions.to_account_info(),
+                },
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+            registry_lib::cpi::verify(cpi_ctx, wallet)?.get()
+        };
+        if !is_verified && !relies_on_claims {
+            return Ok(false);
+        }
+
+        // The verification lapses when the first of the claims expires
+        let mut valid_until = 0;
+        if is_verified {
+            for claim_info in claim_infos() {
+                let claim = Account::<Claim>::try_from(claim_info)?;
+                if claim.valid_until != 0 && (valid_until == 0 || claim.valid_until < valid_until) {
+                    valid_until = claim.valid_until;
+                }
+            }
+        }
+
+        let verification = &mut ctx.accounts.verification;
+        verification.bump = ctx.bumps.verification;
+        verification.registry = ctx.accounts.registry.key();
+        verification.wallet = wallet;
+        verification.identity = identity;
+        verification.is_verified = is_verified;
+        verification.claim_topics = ctx.accounts.registry.claim_topics.clone();
+        verification.claims = claims;
+        verification.valid_until = valid_until;
+        verification.verified_at = Clock::get()?.unix_timestamp;
+
+        emit!(WalletVerified {
+            wallet,
+            identity,
+            is_verified,
+            valid_until,
+        });
+
+        Ok(is_verified)
+    }
+
+    /// Called by Token-2022 on every transfer. Rejects it unless both the
+    /// sender and the receiver were verified within `MAX_VERIFICATION_AGE`.
+    pub fn transfer_hook(ctx: Context<TransferHook>, _amount: u64) -> Result<()> {
+        let registry = &ctx.accounts.registry;
+
+        require_verified(
+            &ctx.accounts.sender_verification,
+            &ctx.accounts.sender_wallet,
+            registry,
+            &ctx.accounts.source_token.owner,
+        )?;
+        require_verified(
+            &ctx.accounts.receiver_verification,
+            &ctx.accounts.receiver_wallet,
+            registry,
+            &ctx.accounts.destination_token.owner,
+        )?;
+
+        Ok(())
+    }
+
+    /// Routes the transfer hook interface's `Execute`, which Anchor's
+    /// discriminators don't match, to `transfer_hook`.
+    pub fn fallback<'info>(program_id: &Pubkey, accounts: &'info [AccountInfo<'info>], data: &[u8]) -> Result<()> {
+        match TransferHookInstruction::unpack(data)? {
+            TransferHookInstruction::Execute { amount } => {
+                __private::__global::transfer_hook(program_id, accounts, &amount.to_le_bytes())
+            }
+            _ => Err(ProgramError::InvalidInstructionData.into()),
+        }
+    }
+}
+
+/// The registry, the factory program, and the sender's and receiver's wallet
+/// links in the registry's factory and verifications, found from the owners
+/// of the source and destination token accounts.
+fn extra_account_metas(registry: &Account<Registry>) -> Result<Vec<ExtraAccountMeta>> {
+    let wallet = |token_account_index| {
+        ExtraAccountMeta::new_external_pda_with_seeds(
+            FACTORY_PROGRAM_ACCOUNT_INDEX,
+            &[
+                Seed::Literal { bytes: WALLET_SEED.to_vec() },
+                Seed::Literal { bytes: registry.factory.to_bytes().to_vec() },
+                Seed::AccountData { account_index: token_account_index, data_index: 32, length: 32 },
+            ],
+            false,
+            false,
+        )
+    };
+    let verification = |token_account_index| {
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal { bytes: VERIFICATION_SEED.to_vec() },
+                Seed::AccountKey { index: REGISTRY_ACCOUNT_INDEX },
+                Seed::AccountData { account_index: token_account_index, data_index: 32, length: 32 },
+            ],
+            false,
+            false,
+        )
+    };
+
+    Ok(vec![
+        ExtraAccountMeta::new_with_pubkey(&registry.key(), false, false)?,
+        ExtraAccountMeta::new_with_pubkey(&factory_lib::ID, false, false)?,
+        wallet(0)?,
+        wallet(2)?,
+        verification(0)?,
+        verification(2)?,
+    ])
+}
+
+/// Requires `wallet` to still be linked to the identity it was verified for,
+/// against the registry's current topics, and before the verification lapses
+/// or gets older than `MAX_VERIFICATION_AGE`.
+fn require_verified(
+    verification_info: &AccountInfo,
+    wallet_account: &AccountInfo,
+    registry: &Account<Registry>,
+    wallet: &Pubkey,
+) -> Result<()> {
+    // Wallets that were never verified have no verification account
+    if verification_info.owner != &crate::ID {
+        return Err(ErrorCode::WalletNotVerified.into());
+    }
+    let verification = Verification::try_deserialize(&mut &verification_info.try_borrow_data()?[..])?;
+
+    if !verification.is_verified
+        || linked_identity(wallet_account, &registry.factory, wallet).ok() != Some(verification.identity)
+        || verification.claim_topics != registry.claim_topics
+    {
+        return Err(ErrorCode::WalletNotVerified.into());
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+    let is_stale = now >= verification.verified_at.saturating_add(MAX_VERIFICATION_AGE);
+    if is_stale || (verification.valid_until != 0 && now >= verification.valid_until) {
+        return Err(ErrorCode::VerificationExpired.into());
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetaList<'info> {
+    /// CHECK: Created and written as an ExtraAccountMetaList, which Anchor doesn't deserialize.
+    #[account(mut, seeds = [EXTRA_ACCOUNT_METAS_SEED, mint.key().as_ref()], bump)]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+    #[account(constraint = mint.mint_authority == COption::Some(authority.key()) @ ErrorCode::Unauthorized)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub registry: Account<'info, Registry>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct VerifyWallet<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = Verification::LEN,
+        seeds = [VERIFICATION_SEED, registry.key().as_ref(), wallet.as_ref()],
+        bump
+    )]
+    pub verification: Account<'info, Verification>,
+    pub registry: Account<'info, Registry>,
+    /// CHECK: The registry factory's `[WALLET_SEED, factory, wallet]` PDA, see `linked_identity`.
+    pub wallet_account: UncheckedAccount<'info>,
+    pub registry_program: Program<'info, RegistryProgram>,
+    pub claim_issuer_program: Program<'info, ClaimIssuer>,
+    /// CHECK: The instructions sysvar, see the registry's `verify`.
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts of the transfer hook interface's `Execute`, followed by the
+/// extra accounts listed for the mint.
+#[derive(Accounts)]
+pub struct TransferHook<'info> {
+    #[account(token::mint = mint)]
+    pub source_token: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(token::mint = mint)]
+    pub destination_token: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: The source token account's owner or delegate, already checked by Token-2022.
+    pub owner: UncheckedAccount<'info>,
+    /// CHECK: The mint's ExtraAccountMetaList, which Token-2022 resolved the accounts below from.
+    #[account(seeds = [EXTRA_ACCOUNT_METAS_SEED, mint.key().as_ref()], bump)]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+    pub registry: Account<'info, Registry>,
+    /// CHECK: The factory program, which owns the wallet links below.
+    #[account(address = factory_lib::ID)]
+    pub factory_program: UncheckedAccount<'info>,
+    /// CHECK: The registry factory's `[WALLET_SEED, factory, wallet]` PDA, see `linked_identity`.
+    pub sender_wallet: UncheckedAccount<'info>,
+    /// CHECK: The registry factory's `[WALLET_SEED, factory, wallet]` PDA, see `linked_identity`.
+    pub receiver_wallet: UncheckedAccount<'info>,
+    /// CHECK: Doesn't exist for wallets that were never verified, see `require_verified`.
+    #[account(seeds = [VERIFICATION_SEED, registry.key().as_ref(), source_token.owner.as_ref()], bump)]
+    pub sender_verification: UncheckedAccount<'info>,
+    /// CHECK: Doesn't exist for wallets that were never verified, see `require_verified`.
+    #[account(seeds = [VERIFICATION_SEED, registry.key().as_ref(), destination_token.owner.as_ref()], bump)]
+    pub receiver_verification: UncheckedAccount<'info>,
+}
+
+/// Outcome of the registry's verification of a wallet.
+#[account]
+pub struct Verification {
+    pub bump: u8,
+    pub registry: Pubkey,
+    pub wallet: Pubkey,
+    pub identity: Pubkey,
+    pub is_verified: bool,
+    pub claim_topics: Vec<u64>,
+    /// The claim the verification relies on for each topic.
+    pub claims: Vec<Pubkey>,
+    pub valid_until: i64,
+    /// When the registry was last asked, see `MAX_VERIFICATION_AGE`.
+    pub verified_at: i64,
+}
+
+impl Verification {
+    const LEN: usize =
+        8 + 1 + 32 + 32 + 32 + 1 + 4 + Registry::MAX_CLAIM_TOPICS * 8 + 4 + Registry::MAX_CLAIM_TOPICS * 32 + 8 + 8;
+}
+
+#[event]
+pub struct WalletVerified {
+    pub wallet: Pubkey,
+    pub identity: Pubkey,
+    pub is_verified: bool,
+    pub valid_until: i64,
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Only the mint authority can do this")]
+    Unauthorized,
+    #[msg("The wallet is not verified")]
+    WalletNotVerified,
+    #[msg("The wallet's verification has expired")]
+    VerificationExpired,
+}
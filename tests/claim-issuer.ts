@@ -0,0 +1,206 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Keypair, SYSVAR_INSTRUCTIONS_PUBKEY } from "@solana/web3.js";
+import { expect } from "chai";
+import { ClaimIssuer } from "../target/types/claim_issuer_lib";
+import {
+  CLAIM,
+  ECDSA,
+  ED25519_SCHEME,
+  claimId,
+  claimMessage,
+  expectError,
+  revokedClaimAddress,
+  signEd25519,
+} from "./utils";
+
+describe("claim_issuer", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.ClaimIssuer as Program<ClaimIssuer>;
+
+  const identityAccount = Keypair.generate();
+  const keyAccount = Keypair.generate();
+  const keys = Keypair.generate();
+  const claimsAccount = Keypair.generate();
+
+  const manager = Keypair.generate();
+  const claimSigner = Keypair.generate();
+  // The identity the issuer vouches for
+  const identity = Keypair.generate().publicKey;
+  const topic = 1;
+  const data = Buffer.from("KYC verified");
+  const { signature } = signEd25519(claimSigner, claimMessage(identity, topic, data));
+
+  const isClaimValid = (message: Buffer, validFrom = 0, validUntil = 0) => {
+    const { signature, instruction } = signEd25519(claimSigner, message);
+
+    return program.methods
+      .isClaimValid(
+        identity,
+        new anchor.BN(topic),
+        claimSigner.publicKey,
+        [...signature],
+        data,
+        new anchor.BN(validFrom),
+        new anchor.BN(validUntil),
+        null,
+      )
+      .accounts({
+        keys: keys.publicKey,
+        revokedClaim: revokedClaimAddress(keys.publicKey, signature),
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([instruction])
+      .view();
+  };
+
+  const keyContext = (signer: Keypair) => ({ keys: keys.publicKey, signer: signer.publicKey });
+
+  before(async () => {
+    await program.methods
+      .initialize(manager.publicKey)
+      .accounts({
+        identityAccount: identityAccount.publicKey,
+        keyAccount: keyAccount.publicKey,
+        keys: keys.publicKey,
+        claimsAccount: claimsAccount.publicKey,
+        user: provider.wallet.publicKey,
+      })
+      .signers([identityAccount, keyAccount, keys, claimsAccount])
+      .rpc();
+
+    await program.methods
+      .addKey(claimSigner.publicKey, CLAIM, ECDSA)
+      .accounts(keyContext(manager))
+      .signers([manager])
+      .rpc();
+
+    await program.methods
+      .addClaim(
+        new anchor.BN(topic),
+        new anchor.BN(ED25519_SCHEME),
+        claimSigner.publicKey,
+        program.programId,
+        [...signature],
+        data,
+        "https://example.com/claim",
+        new anchor.BN(0),
+        new anchor.BN(0),
+        null,
+      )
+      .accounts({
+        claimsAccount: claimsAccount.publicKey,
+        keyContext: keyContext(claimSigner),
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .signers([claimSigner])
+      .rpc();
+  });
+
+  describe("authorization", () => {
+    it("only lets management keys add keys", async () => {
+      await expectError(
+        program.methods
+          .addKey(Keypair.generate().publicKey, CLAIM, ECDSA)
+          .accounts(keyContext(claimSigner))
+          .signers([claimSigner])
+          .rpc(),
+        "InsufficientPermissions",
+      );
+    });
+
+    it("rejects a key that isn't in the keys account", async () => {
+      const stranger = Keypair.generate();
+
+      await expectError(
+        program.methods
+          .addKey(stranger.publicKey, CLAIM, ECDSA)
+          .accounts(keyContext(stranger))
+          .signers([stranger])
+          .rpc(),
+        "InsufficientPermissions",
+      );
+    });
+  });
+
+  describe("is_claim_valid", () => {
+    it("vouches for a claim signed by one of its claim keys", async () => {
+      expect(await isClaimValid(claimMessage(identity, topic, data))).to.be.true;
+    });
+
+    it("doesn't vouch for a signature over another claim", async () => {
+      expect(await isClaimValid(claimMessage(identity, topic + 1, data))).to.be.false;
+    });
+
+    it("doesn't vouch for a claim outside its validity window", async () => {
+      const now = Math.floor(Date.now() / 1000);
+      const validFrom = now - 7200;
+      const validUntil = now - 3600;
+
+      expect(await isClaimValid(claimMessage(identity, topic, data, validFrom, validUntil), validFrom, validUntil)).to
+        .be.false;
+    });
+  });
+
+  describe("revocation", () => {
+    const revokeClaim = (signer = manager) =>
+      program.methods
+        .revokeClaim([...claimId(program.programId, topic)])
+        .accounts({
+          claimsAccount: claimsAccount.publicKey,
+          keyContext: keyContext(signer),
+          revokedClaim: revokedClaimAddress(keys.publicKey, signature),
+          payer: provider.wallet.publicKey,
+        })
+        .signers([signer])
+        .rpc();
+
+    const revokeClaimBySignature = (revoked: Buffer, signer = manager) =>
+      program.methods
+        .revokeClaimBySignature([...revoked])
+        .accounts({
+          keyContext: keyContext(signer),
+          revokedClaim: revokedClaimAddress(keys.publicKey, revoked),
+          payer: provider.wallet.publicKey,
+        })
+        .signers([signer])
+        .rpc();
+
+    it("only lets management keys revoke claims", async () => {
+      await expectError(revokeClaim(claimSigner), "InsufficientPermissions");
+    });
+
+    it("stops vouching for a revoked claim", async () => {
+      await revokeClaim();
+
+      const revokedClaim = await program.account.revokedClaim.fetch(revokedClaimAddress(keys.publicKey, signature));
+      expect(Buffer.from(revokedClaim.signature)).to.deep.equal(signature);
+
+      expect(await isClaimValid(claimMessage(identity, topic, data))).to.be.false;
+    });
+
+    it("rejects revoking a claim twice", async () => {
+      await expectError(revokeClaim(), "ClaimAlreadyRevoked");
+    });
+
+    it("only lets management keys revoke signatures", async () => {
+      const other = signEd25519(claimSigner, claimMessage(identity, topic + 2, data));
+
+      await expectError(revokeClaimBySignature(other.signature, claimSigner), "InsufficientPermissions");
+    });
+
+    it("revokes a claim it doesn't hold by its signature", async () => {
+      const other = signEd25519(claimSigner, claimMessage(identity, topic, Buffer.from("accredited investor")));
+
+      await revokeClaimBySignature(other.signature);
+
+      const isRevoked = await program.methods
+        .isClaimRevoked([...other.signature])
+        .accounts({ keys: keys.publicKey, revokedClaim: revokedClaimAddress(keys.publicKey, other.signature) })
+        .view();
+      expect(isRevoked).to.be.true;
+    });
+  });
+});
@@ -0,0 +1,404 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SYSVAR_INSTRUCTIONS_PUBKEY } from "@solana/web3.js";
+import { expect } from "chai";
+import { Identity } from "../target/types/identity_lib";
+import {
+  ACTION,
+  CLAIM,
+  ED25519,
+  ED25519_SCHEME,
+  MANAGEMENT,
+  airdrop,
+  claimAddress,
+  claimIndexAddress,
+  claimMessage,
+  expectError,
+  identityAddress,
+  keyAddress,
+  purposeKeysAddress,
+  signEd25519,
+} from "./utils";
+
+describe("identity", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.Identity as Program<Identity>;
+
+  const factory = Keypair.generate();
+  const wallet = Keypair.generate();
+  const manager = Keypair.generate();
+  const claimSigner = Keypair.generate();
+  const salt = Buffer.alloc(32, 1);
+  const identity = identityAddress(factory.publicKey, wallet.publicKey, salt);
+
+  const keyContext = (signer: Keypair, signerKey: PublicKey | null = keyAddress(identity, signer.publicKey)) => ({
+    identityAccount: identity,
+    signerKey,
+    signer: signer.publicKey,
+  });
+
+  const addKey = (signer: Keypair, key: PublicKey, purpose: number, keyType = ED25519) =>
+    program.methods
+      .addKey(key, purpose, keyType)
+      .accounts({
+        keyContext: keyContext(signer),
+        keyAccount: keyAddress(identity, key),
+        purposeKeys: purposeKeysAddress(identity, purpose),
+        payer: provider.wallet.publicKey,
+      })
+      .signers([signer])
+      .rpc();
+
+  const addClaim = (topic: number, signature: Buffer, data: Buffer, validFrom = 0, validUntil = 0) =>
+    program.methods
+      .addClaim(
+        new anchor.BN(topic),
+        new anchor.BN(ED25519_SCHEME),
+        claimSigner.publicKey,
+        identity,
+        signature,
+        data,
+        "https://example.com/claim",
+        new anchor.BN(validFrom),
+        new anchor.BN(validUntil),
+        null,
+      )
+      .accounts({
+        claim: claimAddress(identity, identity, topic),
+        claimIndex: claimIndexAddress(identity, topic),
+        keyContext: keyContext(claimSigner),
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        claimIssuerProgram: null,
+        issuerKeys: null,
+        issuerRevokedClaim: null,
+        claimKey: keyAddress(identity, claimSigner.publicKey),
+        evmIssuer: null,
+        payer: provider.wallet.publicKey,
+      })
+      .signers([claimSigner]);
+
+  before(async () => {
+    await program.methods
+      .initialize(wallet.publicKey, [...salt], manager.publicKey)
+      .accounts({
+        identityAccount: identity,
+        managementKey: keyAddress(identity, manager.publicKey),
+        managementKeys: purposeKeysAddress(identity, MANAGEMENT),
+        user: provider.wallet.publicKey,
+        factory: factory.publicKey,
+      })
+      .signers([factory])
+      .rpc();
+
+    await addKey(manager, claimSigner.publicKey, CLAIM);
+  });
+
+  describe("authorization", () => {
+    it("rejects keys added by a signer without a management key", async () => {
+      const stranger = Keypair.generate();
+      await airdrop(provider, stranger.publicKey, 1);
+
+      await expectError(
+        program.methods
+          .addKey(stranger.publicKey, MANAGEMENT, ED25519)
+          .accounts({
+            keyContext: keyContext(stranger, null),
+            keyAccount: keyAddress(identity, stranger.publicKey),
+            purposeKeys: purposeKeysAddress(identity, MANAGEMENT),
+            payer: provider.wallet.publicKey,
+          })
+          .signers([stranger])
+          .rpc(),
+        "InsufficientPermissions",
+      );
+    });
+
+    it("rejects keys added by a key without the management purpose", async () => {
+      await expectError(addKey(claimSigner, Keypair.generate().publicKey, ACTION), "InsufficientPermissions");
+    });
+  });
+
+  describe("signature parsing", () => {
+    const topic = 1;
+    const data = Buffer.from("KYC verified");
+
+    it("rejects a claim without an Ed25519Program instruction", async () => {
+      const { signature } = signEd25519(claimSigner, claimMessage(identity, topic, data));
+
+      await expectError(addClaim(topic, signature, data).rpc(), "SigVerificationFailed");
+    });
+
+    it("rejects a claim signed over another message", async () => {
+      const { signature, instruction } = signEd25519(claimSigner, claimMessage(identity, topic + 1, data));
+
+      await expectError(addClaim(topic, signature, data).preInstructions([instruction]).rpc(), "SigVerificationFailed");
+    });
+
+    it("rejects a claim signed by a key without the claim purpose", async () => {
+      const { signature, instruction } = signEd25519(manager, claimMessage(identity, topic, data));
+
+      await expectError(
+        program.methods
+          .addClaim(
+            new anchor.BN(topic),
+            new anchor.BN(ED25519_SCHEME),
+            manager.publicKey,
+            identity,
+            signature,
+            data,
+            "",
+            new anchor.BN(0),
+            new anchor.BN(0),
+            null,
+          )
+          .accounts({
+            claim: claimAddress(identity, identity, topic),
+            claimIndex: claimIndexAddress(identity, topic),
+            keyContext: keyContext(claimSigner),
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            claimIssuerProgram: null,
+            issuerKeys: null,
+            issuerRevokedClaim: null,
+            claimKey: keyAddress(identity, manager.publicKey),
+            evmIssuer: null,
+            payer: provider.wallet.publicKey,
+          })
+          .preInstructions([instruction])
+          .signers([claimSigner])
+          .rpc(),
+        "InvalidClaim",
+      );
+    });
+
+    it("adds a claim verified by an Ed25519Program instruction", async () => {
+      const { signature, instruction } = signEd25519(claimSigner, claimMessage(identity, topic, data));
+
+      await addClaim(topic, signature, data).preInstructions([instruction]).rpc();
+
+      const claim = await program.account.claim.fetch(claimAddress(identity, identity, topic));
+      expect(claim.issuer.toBase58()).to.equal(identity.toBase58());
+      expect(Buffer.from(claim.signature)).to.deep.equal(signature);
+      expect(Buffer.from(claim.data)).to.deep.equal(data);
+      expect(claim.payer.toBase58()).to.equal(provider.wallet.publicKey.toBase58());
+    });
+
+    it("finds the claim's signature among other Ed25519Program instructions", async () => {
+      const otherTopic = 4;
+      const other = signEd25519(Keypair.generate(), Buffer.from("unrelated message"));
+      const { signature, instruction } = signEd25519(claimSigner, claimMessage(identity, otherTopic, data));
+
+      await addClaim(otherTopic, signature, data).preInstructions([other.instruction, instruction]).rpc();
+
+      const claim = await program.account.claim.fetch(claimAddress(identity, identity, otherTopic));
+      expect(Buffer.from(claim.signature)).to.deep.equal(signature);
+    });
+  });
+
+  describe("validity windows", () => {
+    const data = Buffer.from("accredited investor");
+    const now = () => Math.floor(Date.now() / 1000);
+
+    it("rejects an expired claim", async () => {
+      const topic = 2;
+      const validFrom = now() - 7200;
+      const validUntil = now() - 3600;
+      const { signature, instruction } = signEd25519(
+        claimSigner,
+        claimMessage(identity, topic, data, validFrom, validUntil),
+      );
+
+      await expectError(
+        addClaim(topic, signature, data, validFrom, validUntil).preInstructions([instruction]).rpc(),
+        "ClaimExpired",
+      );
+    });
+
+    it("rejects a claim that is not valid yet", async () => {
+      const topic = 2;
+      const validFrom = now() + 3600;
+      const { signature, instruction } = signEd25519(claimSigner, claimMessage(identity, topic, data, validFrom, 0));
+
+      await expectError(
+        addClaim(topic, signature, data, validFrom, 0).preInstructions([instruction]).rpc(),
+        "ClaimExpired",
+      );
+    });
+
+    it("rejects a window that was not signed", async () => {
+      const topic = 2;
+      const { signature, instruction } = signEd25519(claimSigner, claimMessage(identity, topic, data));
+
+      await expectError(
+        addClaim(topic, signature, data, 0, now() + 3600).preInstructions([instruction]).rpc(),
+        "SigVerificationFailed",
+      );
+    });
+
+    it("adds a claim within its window", async () => {
+      const topic = 2;
+      const validFrom = now() - 3600;
+      const validUntil = now() + 3600;
+      const { signature, instruction } = signEd25519(
+        claimSigner,
+        claimMessage(identity, topic, data, validFrom, validUntil),
+      );
+
+      await addClaim(topic, signature, data, validFrom, validUntil).preInstructions([instruction]).rpc();
+
+      const claim = await program.account.claim.fetch(claimAddress(identity, identity, topic));
+      expect(claim.validFrom.toNumber()).to.equal(validFrom);
+      expect(claim.validUntil.toNumber()).to.equal(validUntil);
+    });
+  });
+
+  describe("removing claims", () => {
+    const topic = 1;
+
+    const removeClaim = (claimPayer: PublicKey, indexPayer = provider.wallet.publicKey) =>
+      program.methods
+        .removeClaim(new anchor.BN(topic), identity)
+        .accounts({
+          claim: claimAddress(identity, identity, topic),
+          claimIndex: claimIndexAddress(identity, topic),
+          keyContext: keyContext(claimSigner),
+          claimPayer,
+          indexPayer,
+        })
+        .signers([claimSigner])
+        .rpc();
+
+    it("rejects a refund to another account than the claim's payer", async () => {
+      await expectError(removeClaim(Keypair.generate().publicKey), "InvalidClaimPayer");
+    });
+
+    it("rejects a refund to another account than the topic index's payer", async () => {
+      await expectError(
+        removeClaim(provider.wallet.publicKey, Keypair.generate().publicKey),
+        "InvalidClaimPayer",
+      );
+    });
+
+    it("refunds the claim's and the emptied topic index's rent to their payer", async () => {
+      const claim = claimAddress(identity, identity, topic);
+      const claimIndex = claimIndexAddress(identity, topic);
+      const rent = await provider.connection.getBalance(claim);
+
+      await removeClaim(provider.wallet.publicKey);
+
+      expect(await provider.connection.getAccountInfo(claim)).to.be.null;
+      expect(await provider.connection.getAccountInfo(claimIndex)).to.be.null;
+      expect(rent).to.be.greaterThan(0);
+    });
+  });
+
+  describe("removing keys", () => {
+    const removeKey = (key: PublicKey, purpose: number) =>
+      program.methods
+        .removeKey(key, purpose)
+        .accounts({
+          keyContext: keyContext(manager),
+          keyAccount: keyAddress(identity, key),
+          purposeKeys: purposeKeysAddress(identity, purpose),
+          payer: provider.wallet.publicKey,
+        })
+        .signers([manager])
+        .rpc();
+
+    it("keeps the last management key", async () => {
+      await expectError(removeKey(manager.publicKey, MANAGEMENT), "InvalidThreshold");
+    });
+
+    it("removes the last claim key", async () => {
+      await removeKey(claimSigner.publicKey, CLAIM);
+
+      expect(await provider.connection.getAccountInfo(keyAddress(identity, claimSigner.publicKey))).to.be.null;
+      const claimKeys = await program.account.purposeKeysAccount.fetch(purposeKeysAddress(identity, CLAIM));
+      expect(claimKeys.keys).to.be.empty;
+    });
+  });
+
+  describe("thresholds", () => {
+    const secondManager = Keypair.generate();
+
+    before(async () => {
+      await addKey(manager, secondManager.publicKey, MANAGEMENT);
+    });
+
+    it("rejects a threshold above the number of keys of the purpose", async () => {
+      await expectError(
+        program.methods
+          .setThreshold(MANAGEMENT, 3)
+          .accounts({
+            identityAccount: identity,
+            signerKey: keyAddress(identity, manager.publicKey),
+            purposeKeys: purposeKeysAddress(identity, MANAGEMENT),
+            signer: manager.publicKey,
+          })
+          .signers([manager])
+          .rpc(),
+        "InvalidThreshold",
+      );
+    });
+
+    it("requires approvals once the management threshold is above one", async () => {
+      await program.methods
+        .setThreshold(MANAGEMENT, 2)
+        .accounts({
+          identityAccount: identity,
+          signerKey: keyAddress(identity, manager.publicKey),
+          purposeKeys: purposeKeysAddress(identity, MANAGEMENT),
+          signer: manager.publicKey,
+        })
+        .signers([manager])
+        .rpc();
+
+      const identityAccount = await program.account.identityAccount.fetch(identity);
+      expect(identityAccount.managementThreshold).to.equal(2);
+
+      await expectError(addKey(manager, Keypair.generate().publicKey, ACTION), "ApprovalThresholdNotMet");
+    });
+  });
+
+  describe("factory management rights", () => {
+    const addKeyAsFactory = (identityAccount: PublicKey) => {
+      const key = Keypair.generate().publicKey;
+      return program.methods
+        .addKey(key, ACTION, ED25519)
+        .accounts({
+          keyContext: { identityAccount, signerKey: null, signer: factory.publicKey },
+          keyAccount: keyAddress(identityAccount, key),
+          purposeKeys: purposeKeysAddress(identityAccount, ACTION),
+          payer: provider.wallet.publicKey,
+        })
+        .signers([factory])
+        .rpc();
+    };
+
+    it("lets the factory manage an identity it initialized", async () => {
+      await addKeyAsFactory(identity);
+    });
+
+    it("doesn't let the factory manage an identity created with management keys", async () => {
+      const managedSalt = Buffer.alloc(32, 2);
+      const managed = identityAddress(factory.publicKey, wallet.publicKey, managedSalt);
+      await program.methods
+        .initializeWithManagementKeys(wallet.publicKey, [...managedSalt], [{ ed25519: { 0: manager.publicKey } }])
+        .accounts({
+          identityAccount: managed,
+          managementKeys: purposeKeysAddress(managed, MANAGEMENT),
+          user: provider.wallet.publicKey,
+          factory: factory.publicKey,
+        })
+        .remainingAccounts([{ pubkey: keyAddress(managed, manager.publicKey), isSigner: false, isWritable: true }])
+        .signers([factory])
+        .rpc();
+
+      const identityAccount = await program.account.identityAccount.fetch(managed);
+      expect(identityAccount.factoryIsManager).to.be.false;
+
+      await expectError(addKeyAsFactory(managed), "InsufficientPermissions");
+    });
+  });
+});
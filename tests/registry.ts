@@ -0,0 +1,141 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SYSVAR_INSTRUCTIONS_PUBKEY } from "@solana/web3.js";
+import { expect } from "chai";
+import { Registry } from "../target/types/registry_lib";
+import { CLAIM_ISSUER_PROGRAM_ID, airdrop, expectError, walletAddress } from "./utils";
+
+describe("registry", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.Registry as Program<Registry>;
+
+  const owner = provider.wallet.publicKey;
+  const registry = PublicKey.findProgramAddressSync([Buffer.from("registry"), owner.toBuffer()], program.programId)[0];
+  const topic = 1;
+  const trustedIssuers = PublicKey.findProgramAddressSync(
+    [Buffer.from("trusted_issuers"), registry.toBuffer(), new anchor.BN(topic).toArrayLike(Buffer, "le", 8)],
+    program.programId,
+  )[0];
+  const issuer = Keypair.generate().publicKey;
+  // The factory whose wallet links the registry verifies
+  const factory = Keypair.generate().publicKey;
+
+  const stranger = Keypair.generate();
+
+  before(async () => {
+    await program.methods.initialize(factory).accounts({ registry, owner }).rpc();
+    await airdrop(provider, stranger.publicKey, 1);
+  });
+
+  describe("claim topics", () => {
+    it("adds a required topic", async () => {
+      await program.methods.addClaimTopic(new anchor.BN(topic)).accounts({ registry, owner }).rpc();
+
+      const topics = await program.methods.getClaimTopics().accounts({ registry }).view();
+      expect(topics.map((t) => t.toNumber())).to.deep.equal([topic]);
+    });
+
+    it("rejects a topic that is already required", async () => {
+      await expectError(
+        program.methods.addClaimTopic(new anchor.BN(topic)).accounts({ registry, owner }).rpc(),
+        "TopicAlreadyRequired",
+      );
+    });
+
+    it("rejects removing a topic that isn't required", async () => {
+      await expectError(
+        program.methods.removeClaimTopic(new anchor.BN(topic + 1)).accounts({ registry, owner }).rpc(),
+        "TopicNotRequired",
+      );
+    });
+
+    it("only lets the owner change topics", async () => {
+      await expectError(
+        program.methods
+          .addClaimTopic(new anchor.BN(topic + 1))
+          .accounts({ registry, owner: stranger.publicKey })
+          .signers([stranger])
+          .rpc(),
+        "ConstraintSeeds",
+      );
+    });
+  });
+
+  describe("trusted issuers", () => {
+    it("trusts an issuer for a topic", async () => {
+      await program.methods.addTrustedIssuer(new anchor.BN(topic), issuer).accounts({ registry, trustedIssuers, owner }).rpc();
+
+      const issuers = await program.methods
+        .getTrustedIssuers(new anchor.BN(topic))
+        .accounts({ registry, trustedIssuers })
+        .view();
+      expect(issuers.map((i) => i.toBase58())).to.deep.equal([issuer.toBase58()]);
+    });
+
+    it("rejects an issuer that is already trusted", async () => {
+      await expectError(
+        program.methods.addTrustedIssuer(new anchor.BN(topic), issuer).accounts({ registry, trustedIssuers, owner }).rpc(),
+        "IssuerAlreadyTrusted",
+      );
+    });
+
+    it("rejects removing an issuer that isn't trusted", async () => {
+      await expectError(
+        program.methods
+          .removeTrustedIssuer(new anchor.BN(topic), Keypair.generate().publicKey)
+          .accounts({ registry, trustedIssuers, owner })
+          .rpc(),
+        "IssuerNotTrusted",
+      );
+    });
+
+    it("removes a trusted issuer", async () => {
+      await program.methods
+        .removeTrustedIssuer(new anchor.BN(topic), issuer)
+        .accounts({ registry, trustedIssuers, owner })
+        .rpc();
+
+      const issuers = await program.methods
+        .getTrustedIssuers(new anchor.BN(topic))
+        .accounts({ registry, trustedIssuers })
+        .view();
+      expect(issuers).to.be.empty;
+    });
+  });
+
+  describe("verify", () => {
+    it("rejects a wallet that isn't linked to an identity", async () => {
+      const wallet = Keypair.generate().publicKey;
+
+      await expectError(
+        program.methods
+          .verify(wallet)
+          .accounts({
+            registry,
+            walletAccount: walletAddress(factory, wallet),
+            claimIssuerProgram: CLAIM_ISSUER_PROGRAM_ID,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          })
+          .view(),
+        "WalletNotLinked",
+      );
+    });
+
+    it("rejects the wallet link of another wallet", async () => {
+      await expectError(
+        program.methods
+          .verify(Keypair.generate().publicKey)
+          .accounts({
+            registry,
+            walletAccount: walletAddress(factory, Keypair.generate().publicKey),
+            claimIssuerProgram: CLAIM_ISSUER_PROGRAM_ID,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          })
+          .view(),
+        "InvalidWalletAccount",
+      );
+    });
+  });
+});
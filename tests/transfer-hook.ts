@@ -0,0 +1,151 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  ExtensionType,
+  TOKEN_2022_PROGRAM_ID,
+  createAssociatedTokenAccount,
+  createInitializeMintInstruction,
+  createInitializeTransferHookInstruction,
+  createTransferCheckedWithTransferHookInstruction,
+  getMintLen,
+  mintTo,
+} from "@solana/spl-token";
+import { Keypair, PublicKey, SystemProgram, Transaction, sendAndConfirmTransaction } from "@solana/web3.js";
+import { expect } from "chai";
+import { Registry } from "../target/types/registry_lib";
+import { TransferHook } from "../target/types/transfer_hook_lib";
+import { airdrop, expectError } from "./utils";
+
+describe("transfer_hook", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.TransferHook as Program<TransferHook>;
+  const registryProgram = anchor.workspace.Registry as Program<Registry>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const registryOwner = Keypair.generate();
+  const registry = PublicKey.findProgramAddressSync(
+    [Buffer.from("registry"), registryOwner.publicKey.toBuffer()],
+    registryProgram.programId,
+  )[0];
+
+  const mint = Keypair.generate();
+  const mintAuthority = Keypair.generate();
+  const decimals = 6;
+  const extraAccountMetaList = PublicKey.findProgramAddressSync(
+    [Buffer.from("extra-account-metas"), mint.publicKey.toBuffer()],
+    program.programId,
+  )[0];
+
+  const sender = Keypair.generate();
+  const receiver = Keypair.generate();
+
+  const initializeExtraAccountMetaList = (authority: Keypair) =>
+    program.methods
+      .initializeExtraAccountMetaList()
+      .accounts({
+        extraAccountMetaList,
+        mint: mint.publicKey,
+        registry,
+        authority: authority.publicKey,
+        payer: provider.wallet.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+
+  before(async () => {
+    await airdrop(provider, registryOwner.publicKey, 1);
+    await registryProgram.methods
+      .initialize(Keypair.generate().publicKey)
+      .accounts({ registry, owner: registryOwner.publicKey })
+      .signers([registryOwner])
+      .rpc();
+
+    const mintLen = getMintLen([ExtensionType.TransferHook]);
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+    await sendAndConfirmTransaction(
+      provider.connection,
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: mint.publicKey,
+          space: mintLen,
+          lamports,
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializeTransferHookInstruction(
+          mint.publicKey,
+          mintAuthority.publicKey,
+          program.programId,
+          TOKEN_2022_PROGRAM_ID,
+        ),
+        createInitializeMintInstruction(mint.publicKey, decimals, mintAuthority.publicKey, null, TOKEN_2022_PROGRAM_ID),
+      ),
+      [payer, mint],
+    );
+  });
+
+  describe("initialize_extra_account_meta_list", () => {
+    it("only lets the mint authority hook transfers to a registry", async () => {
+      await expectError(initializeExtraAccountMetaList(Keypair.generate()), "Unauthorized");
+    });
+
+    it("lists the accounts transfers need", async () => {
+      await initializeExtraAccountMetaList(mintAuthority);
+
+      const account = await provider.connection.getAccountInfo(extraAccountMetaList);
+      expect(account.owner.toBase58()).to.equal(program.programId.toBase58());
+    });
+  });
+
+  describe("transfers", () => {
+    it("rejects transfers between wallets the registry never verified", async () => {
+      const source = await createAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        mint.publicKey,
+        sender.publicKey,
+        undefined,
+        TOKEN_2022_PROGRAM_ID,
+      );
+      const destination = await createAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        mint.publicKey,
+        receiver.publicKey,
+        undefined,
+        TOKEN_2022_PROGRAM_ID,
+      );
+      await mintTo(
+        provider.connection,
+        payer,
+        mint.publicKey,
+        source,
+        mintAuthority,
+        1_000_000,
+        [],
+        undefined,
+        TOKEN_2022_PROGRAM_ID,
+      );
+
+      const transfer = await createTransferCheckedWithTransferHookInstruction(
+        provider.connection,
+        source,
+        mint.publicKey,
+        destination,
+        sender.publicKey,
+        BigInt(1_000),
+        decimals,
+        [],
+        "confirmed",
+        TOKEN_2022_PROGRAM_ID,
+      );
+
+      await expectError(
+        sendAndConfirmTransaction(provider.connection, new Transaction().add(transfer), [payer, sender]),
+        "WalletNotVerified",
+      );
+    });
+  });
+});
@@ -0,0 +1,103 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Ed25519Program, Keypair, LAMPORTS_PER_SOL, PublicKey } from "@solana/web3.js";
+import { createHash } from "crypto";
+import { expect } from "chai";
+
+export const IDENTITY_PROGRAM_ID = new PublicKey("Gono8cHfm7zUfgdmSEcuvCx2WsN2LEK2ymtdoS67cVDK");
+export const CLAIM_ISSUER_PROGRAM_ID = new PublicKey("3BWQvZ8vUQewDzAcDmoYa2PzKFazgaCBnKQNFQx15hXM");
+export const FACTORY_PROGRAM_ID = new PublicKey("EjTQazH7zvwvBFDkbJRnpvQfjuQBqjHTdbYE25iaxZoJ");
+
+export const MANAGEMENT = 1;
+export const ACTION = 2;
+export const CLAIM = 3;
+
+export const ECDSA = 1;
+export const ED25519 = 3;
+
+/** Claim scheme of claims signed by Ed25519 keys. */
+export const ED25519_SCHEME = 4;
+
+const sha256 = (...parts: Buffer[]) => createHash("sha256").update(Buffer.concat(parts)).digest();
+
+const u32 = (value: number) => {
+  const buffer = Buffer.alloc(4);
+  buffer.writeUInt32LE(value);
+  return buffer;
+};
+
+const u64 = (value: number | bigint) => {
+  const buffer = Buffer.alloc(8);
+  buffer.writeBigUInt64LE(BigInt(value));
+  return buffer;
+};
+
+const i64 = (value: number | bigint) => {
+  const buffer = Buffer.alloc(8);
+  buffer.writeBigInt64LE(BigInt(value));
+  return buffer;
+};
+
+const identityPda = (...seeds: Buffer[]) => PublicKey.findProgramAddressSync(seeds, IDENTITY_PROGRAM_ID)[0];
+
+export const identityAddress = (factory: PublicKey, wallet: PublicKey, salt: Buffer) =>
+  identityPda(Buffer.from("identity"), factory.toBuffer(), wallet.toBuffer(), salt);
+
+export const keyAddress = (identity: PublicKey, key: PublicKey) =>
+  identityPda(Buffer.from("key"), identity.toBuffer(), sha256(key.toBuffer()));
+
+export const purposeKeysAddress = (identity: PublicKey, purpose: number) =>
+  identityPda(Buffer.from("keys"), identity.toBuffer(), u32(purpose));
+
+export const claimId = (issuer: PublicKey, topic: number) => sha256(issuer.toBuffer(), u64(topic));
+
+export const claimAddress = (identity: PublicKey, issuer: PublicKey, topic: number) =>
+  identityPda(Buffer.from("claim"), identity.toBuffer(), claimId(issuer, topic));
+
+export const claimIndexAddress = (identity: PublicKey, topic: number) =>
+  identityPda(Buffer.from("claims"), identity.toBuffer(), u64(topic));
+
+export const revokedClaimAddress = (keys: PublicKey, signature: Buffer) =>
+  PublicKey.findProgramAddressSync([Buffer.from("revoked"), keys.toBuffer(), sha256(signature)], CLAIM_ISSUER_PROGRAM_ID)[0];
+
+export const walletAddress = (factory: PublicKey, wallet: PublicKey) =>
+  PublicKey.findProgramAddressSync([Buffer.from("wallet"), factory.toBuffer(), wallet.toBuffer()], FACTORY_PROGRAM_ID)[0];
+
+/** The message Ed25519 claim keys sign, see `claim_issuer::claim_message`. */
+export const claimMessage = (identity: PublicKey, topic: number, data: Buffer, validFrom = 0, validUntil = 0) => {
+  const parts = [identity.toBuffer(), u64(topic), u32(data.length), data];
+  if (validFrom !== 0 || validUntil !== 0) {
+    parts.push(i64(validFrom), i64(validUntil));
+  }
+  return Buffer.concat(parts);
+};
+
+/** Signs `message` with `signer`, returning the signature and the Ed25519Program instruction verifying it. */
+export const signEd25519 = (signer: Keypair, message: Buffer) => {
+  const instruction = Ed25519Program.createInstructionWithPrivateKey({
+    privateKey: signer.secretKey,
+    message,
+  });
+  // The signature follows the 16-byte offsets header and the 32-byte public key
+  const signature = Buffer.from(instruction.data.subarray(48, 112));
+
+  return { signature, instruction };
+};
+
+export const airdrop = async (provider: anchor.AnchorProvider, to: PublicKey, sol = 10) => {
+  const signature = await provider.connection.requestAirdrop(to, sol * LAMPORTS_PER_SOL);
+  const latestBlockhash = await provider.connection.getLatestBlockhash();
+  await provider.connection.confirmTransaction({ signature, ...latestBlockhash });
+};
+
+/** Expects `promise` to fail with the program error named `code`. */
+export const expectError = async (promise: Promise<unknown>, code: string) => {
+  try {
+    await promise;
+  } catch (err) {
+    // Simulated views report their logs through the simulation response
+    const logs: string[] = err.logs ?? err.simulationResponse?.logs ?? [];
+    expect(`${err}\n${logs.join("\n")}`).to.include(code);
+    return;
+  }
+  expect.fail(`expected the transaction to fail with ${code}`);
+};